@@ -5,7 +5,7 @@ use openssl::symm::{Cipher, Crypter, Mode};
 use rand::{thread_rng, Rng};
 use std::collections::HashSet;
 
-const AES_BLOCK_SIZE: usize = 16usize;
+pub const AES_BLOCK_SIZE: usize = 16usize;
 
 // CryptoPals Set 1 Challenge 7
 pub fn decrypt_aes_ecb(ciphertext: &Vec<u8>, key: &Vec<u8>, iv: Option<&[u8]>) -> Vec<u8> {
@@ -95,7 +95,7 @@ pub fn decrypt_aes_cbc(
 pub fn encrypt_aes_cbc(plaintext: &Vec<u8>, key: &Vec<u8>, initial_iv: &Vec<u8>) -> Vec<u8> {
     let mut ciphertext: Vec<u8> = Vec::new();
     let padded_data = pkcs_7_pad(&plaintext, &AES_BLOCK_SIZE);
-    for i in (0..plaintext.len()).step_by(AES_BLOCK_SIZE) {
+    for i in (0..padded_data.len()).step_by(AES_BLOCK_SIZE) {
         let block = padded_data[i..i + AES_BLOCK_SIZE].to_vec();
         // let padded_data = pkcs_7_pad(&block, AES_BLOCK_SIZE);
         let xor_with = match i {
@@ -109,6 +109,20 @@ pub fn encrypt_aes_cbc(plaintext: &Vec<u8>, key: &Vec<u8>, initial_iv: &Vec<u8>)
     ciphertext
 }
 
+// CryptoPals Set 3 Challenge 18
+// The counter block is a 64 bit little-endian nonce followed by a 64 bit
+// little-endian block count, encrypting and decrypting are the same.
+pub fn aes_ctr(data: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
+    let counter_blocks: Vec<u8> = (0..data.len().div_ceil(AES_BLOCK_SIZE) as u64)
+        .flat_map(|counter| nonce.to_le_bytes().into_iter().chain(counter.to_le_bytes()))
+        .collect();
+    let keystream = encrypt_aes_ecb(&counter_blocks, &key.to_vec(), None);
+    data.iter()
+        .zip(keystream.iter())
+        .map(|(byte, key_byte)| byte ^ key_byte)
+        .collect()
+}
+
 // TODO Acting non-deterministic need to fix that should get it 100% of the time.
 // Essentially the problem seems to revolve around the fact it is miss classifying CBC mode for ECB mode.
 // Which will then cause it to incorrectly decrypt the value (because it chooses a ECB decrypt mode).
@@ -0,0 +1,340 @@
+// Arithmetic (+, -, *, /, %) comes from the operator implementations on
+// these types, everything in this module builds on top of them.
+pub use num_bigint::{BigInt, BigUint};
+use num_bigint::{RandBigInt, Sign};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use rand::{thread_rng, Rng};
+
+/// Interpret a big-endian Vec of bytes as an unsigned integer.
+pub fn bytes_to_bigint(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+/// Convert an unsigned integer to its minimal big-endian Vec of bytes.
+/// Zero is returned as a single zero byte.
+pub fn bigint_to_bytes(n: &BigUint) -> Vec<u8> {
+    n.to_bytes_be()
+}
+
+/// Convert an unsigned integer to a big-endian Vec of bytes that is
+/// left padded with zeros to exactly len bytes. Needed whenever a
+/// fixed width encoding matters, e.g. an RSA block the size of the modulus.
+pub fn bigint_to_bytes_padded(n: &BigUint, len: usize) -> Result<Vec<u8>, &'static str> {
+    let bytes = if n.is_zero() {
+        Vec::new()
+    } else {
+        n.to_bytes_be()
+    };
+    if bytes.len() > len {
+        return Err("Integer does not fit in the requested length");
+    }
+    let mut padded = vec![0u8; len - bytes.len()];
+    padded.extend_from_slice(&bytes);
+    Ok(padded)
+}
+
+/// Divide a by b returning the quotient and the remainder.
+pub fn divmod(a: &BigUint, b: &BigUint) -> Result<(BigUint, BigUint), &'static str> {
+    if b.is_zero() {
+        return Err("Division by zero");
+    }
+    Ok(a.div_rem(b))
+}
+
+/// Reduce a signed integer into the range [0, m).
+pub fn modulo(a: &BigInt, m: &BigUint) -> BigUint {
+    let m = BigInt::from(m.clone());
+    a.mod_floor(&m).to_biguint().unwrap()
+}
+
+/// Calculate base^exp mod m using square and multiply.
+pub fn mod_exp(base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+    base.modpow(exp, m)
+}
+
+/// Greatest common divisor of a and b.
+pub fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    let mut a = a.clone();
+    let mut b = b.clone();
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Extended Euclidean algorithm. Returns (g, x, y) such that
+/// a*x + b*y = g where g is the gcd of a and b.
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_s, mut s) = (BigInt::one(), BigInt::zero());
+    let (mut old_t, mut t) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let quotient = old_r.div_floor(&r);
+        let next_r = &old_r - &quotient * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_s = &old_s - &quotient * &s;
+        old_s = std::mem::replace(&mut s, next_s);
+        let next_t = &old_t - &quotient * &t;
+        old_t = std::mem::replace(&mut t, next_t);
+    }
+
+    if old_r.sign() == Sign::Minus {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// Find x such that a*x = 1 mod m using the extended Euclidean algorithm.
+pub fn mod_inverse(a: &BigUint, m: &BigUint) -> Result<BigUint, &'static str> {
+    if m.is_zero() {
+        return Err("Modulus must be non-zero");
+    }
+    let (g, x, _) = extended_gcd(&BigInt::from(a.clone()), &BigInt::from(m.clone()));
+    if !g.is_one() {
+        return Err("No modular inverse exists");
+    }
+    Ok(modulo(&x, m))
+}
+
+/// Square root of a modulo an odd prime p with Tonelli-Shanks. Returns None
+/// when a is not a quadratic residue.
+pub fn mod_sqrt(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let a = a % p;
+    if a.is_zero() {
+        return Some(a);
+    }
+    let half = (p - 1u32) >> 1;
+    if !mod_exp(&a, &half, p).is_one() {
+        return None;
+    }
+
+    // p - 1 = 2^s * q with q odd, and any non-residue z
+    let s = (p - 1u32).trailing_zeros().unwrap();
+    let q = (p - 1u32) >> s;
+    let mut z = BigUint::from(2u32);
+    while mod_exp(&z, &half, p).is_one() {
+        z += 1u32;
+    }
+
+    let mut m = s;
+    let mut c = mod_exp(&z, &q, p);
+    let mut t = mod_exp(&a, &q, p);
+    let mut root = mod_exp(&a, &((&q + 1u32) >> 1), p);
+    while !t.is_one() {
+        let mut i = 0;
+        let mut square = t.clone();
+        while !square.is_one() {
+            square = &square * &square % p;
+            i += 1;
+        }
+        let b = mod_exp(&c, &(BigUint::one() << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        root = root * b % p;
+    }
+    Some(root)
+}
+
+/// Integer k-th root of n, rounded down.
+pub fn kth_root(n: &BigUint, k: u32) -> BigUint {
+    n.nth_root(k)
+}
+
+/// Integer k-th root of n only if n is a perfect k-th power.
+pub fn exact_kth_root(n: &BigUint, k: u32) -> Option<BigUint> {
+    let root = kth_root(n, k);
+    if root.pow(k) == *n {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+/// Uniformly random integer in the range [low, high).
+pub fn random_range(low: &BigUint, high: &BigUint) -> BigUint {
+    thread_rng().gen_biguint_range(low, high)
+}
+
+/// Uniformly random integer with exactly the given amount of bits,
+/// meaning the top bit is always set. Zero bits give zero.
+pub fn random_bits(bits: u64) -> BigUint {
+    if bits == 0 {
+        return BigUint::zero();
+    }
+    let mut n = thread_rng().gen_biguint(bits);
+    n.set_bit(bits - 1, true);
+    n
+}
+
+const SMALL_PRIMES: [u32; 24] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+/// Miller-Rabin probabilistic primality test. Each round with a random
+/// witness lowers the chance of a composite slipping through by 4.
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let two = BigUint::from(2u32);
+    if *n < two {
+        return false;
+    }
+    if *n == two {
+        return true;
+    }
+    if n.is_even() {
+        return false;
+    }
+    for prime in SMALL_PRIMES.iter() {
+        let prime = BigUint::from(*prime);
+        if *n == prime {
+            return true;
+        }
+        if (n % &prime).is_zero() {
+            return false;
+        }
+    }
+
+    // Write n - 1 as 2^s * d with d odd
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap();
+    let d = &n_minus_one >> s;
+
+    'witness: for _ in 0..rounds {
+        let a = random_range(&two, &n_minus_one);
+        let mut x = mod_exp(&a, &d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = mod_exp(&x, &two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Generate a random probable prime with exactly the given amount of bits.
+/// The top two bits are set so the product of two such primes has twice
+/// the bits.
+pub fn generate_prime(bits: u64) -> Result<BigUint, &'static str> {
+    if bits < 2 {
+        return Err("Prime needs at least 2 bits");
+    }
+    loop {
+        let mut candidate = random_bits(bits);
+        candidate.set_bit(bits - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate, 40) {
+            return Ok(candidate);
+        }
+    }
+}
+
+/// Divide out every prime below bound from n by trial division. Returns
+/// the primes found with their multiplicity and the remaining cofactor.
+pub fn trial_division(n: &BigUint, bound: u64) -> (Vec<(u64, u32)>, BigUint) {
+    let mut remaining = n.clone();
+    let mut factors = Vec::new();
+    for d in 2..bound {
+        if remaining.is_one() {
+            break;
+        }
+        let mut exponent = 0;
+        while (&remaining % d).is_zero() {
+            remaining /= d;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factors.push((d, exponent));
+        }
+    }
+    (factors, remaining)
+}
+
+/// All primes below bound with the sieve of Eratosthenes.
+pub fn primes_below(bound: u64) -> Vec<u64> {
+    let mut composite = vec![false; bound as usize];
+    let mut primes = Vec::new();
+    for n in 2..bound {
+        if composite[n as usize] {
+            continue;
+        }
+        primes.push(n);
+        for multiple in (n * n..bound).step_by(n as usize) {
+            composite[multiple as usize] = true;
+        }
+    }
+    primes
+}
+
+/// A random prime p with exactly the given amount of bits where p - 1 is
+/// 2 times distinct odd primes below 2^16, apart from one that may be up
+/// to 2^20 to hit the size. Primes in `avoid` are left out. Returns p and
+/// the prime factors of p - 1. Discrete logs mod such a p are easy.
+pub fn generate_smooth_prime(bits: u64, avoid: &[u64]) -> (BigUint, Vec<u64>) {
+    let pool: Vec<u64> = primes_below(1 << 16)
+        .into_iter()
+        .filter(|r| *r > 2 && !avoid.contains(r))
+        .collect();
+    let mut rng = thread_rng();
+    loop {
+        let mut factors = vec![2u64];
+        let mut product = BigUint::from(2u32);
+        while product.bits() + 20 < bits {
+            let r = pool[rng.gen_range(0..pool.len())];
+            if !factors.contains(&r) {
+                factors.push(r);
+                product *= r;
+            }
+        }
+
+        // The last factor f has to put product * f + 1 in [2^(bits-1), 2^bits).
+        let low = ((BigUint::one() << (bits - 1)) + &product - 1u32) / &product;
+        let high = ((BigUint::one() << bits) - 2u32) / &product;
+        if low >= high {
+            continue;
+        }
+        let last = random_range(&low, &high);
+        let r = match last.to_u64() {
+            Some(r) if r > 2 && !factors.contains(&r) && !avoid.contains(&r) => r,
+            _ => continue,
+        };
+        if !is_probable_prime(&last, 20) {
+            continue;
+        }
+        let p = product * r + 1u32;
+        if is_probable_prime(&p, 40) {
+            factors.push(r);
+            return (p, factors);
+        }
+    }
+}
+
+/// Chinese Remainder Theorem. Find the unique x modulo the product of the
+/// moduli with x = residues[i] mod moduli[i]. The moduli have to be
+/// pairwise coprime. Returns x together with the product of the moduli.
+pub fn chinese_remainder(
+    residues: &[BigUint],
+    moduli: &[BigUint],
+) -> Result<(BigUint, BigUint), &'static str> {
+    if residues.len() != moduli.len() || moduli.is_empty() {
+        return Err("Need the same non-zero amount of residues and moduli");
+    }
+    let product: BigUint = moduli.iter().product();
+    let mut result = BigUint::zero();
+    for (residue, modulus) in residues.iter().zip(moduli.iter()) {
+        let partial = &product / modulus;
+        let inverse = mod_inverse(&(&partial % modulus), modulus)
+            .map_err(|_| "Moduli are not pairwise coprime")?;
+        result += residue * &partial * inverse;
+    }
+    Ok((result % &product, product))
+}
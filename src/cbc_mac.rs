@@ -0,0 +1,281 @@
+use crate::aes::{encrypt_aes_cbc, encrypt_aes_ecb, AES_BLOCK_SIZE};
+use crate::utils::pkcs_7_pad;
+use crate::xor::fixed_xor;
+use rand::{thread_rng, Rng};
+use std::str::FromStr;
+
+/// AES-128 CBC-MAC, the last ciphertext block of the PKCS#7 padded message.
+pub fn cbc_mac(message: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
+    let ciphertext = encrypt_aes_cbc(&message.to_vec(), &key.to_vec(), &iv.to_vec());
+    ciphertext[ciphertext.len() - AES_BLOCK_SIZE..].to_vec()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub from: u32,
+    pub to: u32,
+    pub amount: u64,
+}
+
+/// The web client of the toy bank. It shares the MAC key with the API
+/// server and only signs transfers out of the account the user logged in as.
+pub struct TransferClient {
+    key: Vec<u8>,
+    account: u32,
+}
+
+impl TransferClient {
+    pub fn new(key: &[u8], account: u32) -> TransferClient {
+        TransferClient {
+            key: key.to_vec(),
+            account,
+        }
+    }
+
+    /// First protocol version, `from=#&to=#&amount=#` || IV || MAC with a
+    /// fresh IV for every request.
+    pub fn sign_transfer(&self, to: u32, amount: u64) -> Vec<u8> {
+        let iv: [u8; AES_BLOCK_SIZE] = thread_rng().gen();
+        let mut request = format!("from={}&to={}&amount={}", self.account, to, amount).into_bytes();
+        let mac = cbc_mac(&request, &self.key, &iv);
+        request.extend_from_slice(&iv);
+        request.extend(mac);
+        request
+    }
+
+    /// Second protocol version, `from=#&tx_list=#:#(;#:#)*` || MAC with a
+    /// fixed zero IV.
+    pub fn sign_transfers(&self, transactions: &[(u32, u64)]) -> Vec<u8> {
+        let tx_list: Vec<String> = transactions
+            .iter()
+            .map(|(to, amount)| format!("{}:{}", to, amount))
+            .collect();
+        let mut request =
+            format!("from={}&tx_list={}", self.account, tx_list.join(";")).into_bytes();
+        let mac = cbc_mac(&request, &self.key, &[0u8; AES_BLOCK_SIZE]);
+        request.extend(mac);
+        request
+    }
+}
+
+/// The API server, an in-process stand-in for the backend that moves the
+/// money once the MAC checks out.
+pub struct TransferServer {
+    key: Vec<u8>,
+}
+
+impl TransferServer {
+    pub fn new(key: &[u8]) -> TransferServer {
+        TransferServer { key: key.to_vec() }
+    }
+
+    pub fn process_transfer(&self, request: &[u8]) -> Result<Transfer, &'static str> {
+        if request.len() < 2 * AES_BLOCK_SIZE {
+            return Err("Request is too short");
+        }
+        let (message, tail) = request.split_at(request.len() - 2 * AES_BLOCK_SIZE);
+        let (iv, mac) = tail.split_at(AES_BLOCK_SIZE);
+        if cbc_mac(message, &self.key, iv) != mac {
+            return Err("Invalid MAC");
+        }
+
+        let message = std::str::from_utf8(message).map_err(|_| "Message is not UTF-8")?;
+        let (mut from, mut to, mut amount) = (None, None, None);
+        for pair in message.split('&') {
+            match pair.split_once('=') {
+                Some(("from", value)) => from = value.parse().ok(),
+                Some(("to", value)) => to = value.parse().ok(),
+                Some(("amount", value)) => amount = value.parse().ok(),
+                _ => return Err("Malformed message"),
+            }
+        }
+        match (from, to, amount) {
+            (Some(from), Some(to), Some(amount)) => Ok(Transfer { from, to, amount }),
+            _ => Err("Malformed message"),
+        }
+    }
+
+    /// Like plenty of real parsers this one is lenient: transactions it
+    /// cannot read are skipped instead of failing the whole request.
+    pub fn process_transfers(&self, request: &[u8]) -> Result<Vec<Transfer>, &'static str> {
+        if request.len() < AES_BLOCK_SIZE {
+            return Err("Request is too short");
+        }
+        let (message, mac) = request.split_at(request.len() - AES_BLOCK_SIZE);
+        if cbc_mac(message, &self.key, &[0u8; AES_BLOCK_SIZE]) != mac {
+            return Err("Invalid MAC");
+        }
+
+        let prefix = "from=".as_bytes();
+        let separator = "&tx_list=".as_bytes();
+        if !message.starts_with(prefix) {
+            return Err("Malformed message");
+        }
+        let split = message
+            .windows(separator.len())
+            .position(|window| window == separator)
+            .ok_or("Malformed message")?;
+        let from = parse_number(&message[prefix.len()..split]).ok_or("Malformed message")?;
+
+        let transfers: Vec<Transfer> = message[split + separator.len()..]
+            .split(|byte| *byte == b';')
+            .filter_map(|transaction| {
+                let colon = transaction.iter().position(|byte| *byte == b':')?;
+                Some(Transfer {
+                    from,
+                    to: parse_number(&transaction[..colon])?,
+                    amount: parse_number(&transaction[colon + 1..])?,
+                })
+            })
+            .collect();
+        if transfers.is_empty() {
+            return Err("No valid transactions");
+        }
+        Ok(transfers)
+    }
+}
+
+fn parse_number<T: FromStr>(digits: &[u8]) -> Option<T> {
+    if digits.is_empty() || !digits.iter().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// Forge a first version request from the victim's account. Given a request
+/// the attacker signed for their own account, swap the from field and fix
+/// up the IV so the first CBC input, IV ^ P1, stays the same. Only works
+/// while the change stays inside the first block.
+pub fn forge_transfer_with_iv(request: &[u8], victim: u32) -> Result<Vec<u8>, &'static str> {
+    if request.len() < 2 * AES_BLOCK_SIZE {
+        return Err("Request is too short");
+    }
+    let (message, tail) = request.split_at(request.len() - 2 * AES_BLOCK_SIZE);
+    let (iv, mac) = tail.split_at(AES_BLOCK_SIZE);
+
+    let message = std::str::from_utf8(message).map_err(|_| "Message is not UTF-8")?;
+    let (from_field, rest) = message.split_once('&').ok_or("Malformed message")?;
+    let forged = format!("from={}&{}", victim, rest).into_bytes();
+    if forged.len() != message.len() || from_field.len() >= AES_BLOCK_SIZE {
+        return Err("The account ids have to be the same length");
+    }
+
+    let difference = fixed_xor(
+        &message.as_bytes()[..AES_BLOCK_SIZE].to_vec(),
+        &forged[..AES_BLOCK_SIZE].to_vec(),
+    );
+    let mut request = forged;
+    request.extend(fixed_xor(&iv.to_vec(), &difference));
+    request.extend_from_slice(mac);
+    Ok(request)
+}
+
+/// Forge a second version request by length extension. The victim's MAC is
+/// the CBC state after their padded message, so xoring it into the first
+/// block of the attacker's message continues the chain exactly as if the
+/// attacker's message had started from the zero IV. The glued on
+/// transactions then carry the attacker's MAC. The first block of the
+/// attacker's message turns into garbage, so it should only hold filler.
+pub fn forge_transfers_with_length_extension(
+    victim_request: &[u8],
+    attacker_request: &[u8],
+) -> Result<Vec<u8>, &'static str> {
+    if victim_request.len() < AES_BLOCK_SIZE || attacker_request.len() < 2 * AES_BLOCK_SIZE {
+        return Err("Request is too short");
+    }
+    let (victim_message, victim_mac) =
+        victim_request.split_at(victim_request.len() - AES_BLOCK_SIZE);
+    let (attacker_message, attacker_mac) =
+        attacker_request.split_at(attacker_request.len() - AES_BLOCK_SIZE);
+
+    let mut request = pkcs_7_pad(&victim_message.to_vec(), &AES_BLOCK_SIZE);
+    request.extend(fixed_xor(
+        &attacker_message[..AES_BLOCK_SIZE].to_vec(),
+        &victim_mac.to_vec(),
+    ));
+    request.extend_from_slice(&attacker_message[AES_BLOCK_SIZE..]);
+    request.extend_from_slice(attacker_mac);
+    Ok(request)
+}
+
+/// Key of the CBC-MAC hash from challenge 50.
+pub const CBC_MAC_HASH_KEY: &[u8] = b"YELLOW SUBMARINE";
+
+/// CBC-MAC with a fixed key and a zero IV misused as a hash function.
+pub fn cbc_mac_hash(message: &[u8]) -> Vec<u8> {
+    cbc_mac(message, CBC_MAC_HASH_KEY, &[0u8; AES_BLOCK_SIZE])
+}
+
+fn is_printable_ascii(bytes: &[u8]) -> bool {
+    bytes.iter().all(|byte| (0x20..0x7f).contains(byte))
+}
+
+// The n-th block of printable ASCII, reading n in base 95
+fn printable_block(mut n: u64) -> Vec<u8> {
+    let mut block = vec![b' '; AES_BLOCK_SIZE];
+    for byte in block.iter_mut() {
+        *byte += (n % 95) as u8;
+        n /= 95;
+    }
+    block
+}
+
+/// Find a message that starts with the given prefix and has the same
+/// CBC-MAC hash as the original. The prefix is padded with spaces to a
+/// block boundary, then come a filler block F and a glue block G chosen so
+/// that G = E(state ^ F) ^ O1, which puts the CBC chain back where the
+/// original's first block O1 left it. The rest of the original follows
+/// unchanged. Filler blocks are searched until the glue is printable ASCII
+/// as well, roughly (256 / 95)^16 ~ 2^23 block encryptions.
+///
+/// For JavaScript the prefix should end in `//`, the rest of the line is then
+/// a comment that the original's own newline terminates.
+pub fn forge_cbc_mac_hash_collision(
+    original: &[u8],
+    prefix: &[u8],
+) -> Result<Vec<u8>, &'static str> {
+    if original.len() < AES_BLOCK_SIZE {
+        return Err("Original message has to be at least one block long");
+    }
+    if !is_printable_ascii(prefix) {
+        return Err("Prefix has to be printable ASCII");
+    }
+    let mut forged = prefix.to_vec();
+    forged.resize(prefix.len().div_ceil(AES_BLOCK_SIZE) * AES_BLOCK_SIZE, b' ');
+    let state = match forged.len() {
+        0 => vec![0u8; AES_BLOCK_SIZE],
+        len => {
+            let ciphertext = encrypt_aes_cbc(
+                &forged,
+                &CBC_MAC_HASH_KEY.to_vec(),
+                &vec![0u8; AES_BLOCK_SIZE],
+            );
+            ciphertext[len - AES_BLOCK_SIZE..len].to_vec()
+        }
+    };
+
+    // Encrypt candidates in batches, one call per block is dominated by
+    // setting up the cipher
+    const BATCH: u64 = 4096;
+    let first_block = original[..AES_BLOCK_SIZE].to_vec();
+    for batch in 0u64.. {
+        let fillers: Vec<Vec<u8>> = (batch * BATCH..(batch + 1) * BATCH)
+            .map(printable_block)
+            .collect();
+        let inputs: Vec<u8> = fillers
+            .iter()
+            .flat_map(|filler| fixed_xor(filler, &state))
+            .collect();
+        let outputs = encrypt_aes_ecb(&inputs, &CBC_MAC_HASH_KEY.to_vec(), None);
+        for (filler, output) in fillers.iter().zip(outputs.chunks(AES_BLOCK_SIZE)) {
+            let glue = fixed_xor(&output.to_vec(), &first_block);
+            if is_printable_ascii(&glue) {
+                forged.extend(filler);
+                forged.extend(glue);
+                forged.extend_from_slice(&original[AES_BLOCK_SIZE..]);
+                return Ok(forged);
+            }
+        }
+    }
+    Err("Search space exhausted")
+}
@@ -0,0 +1,183 @@
+use crate::aes::{aes_ctr, encrypt_aes_cbc, AES_BLOCK_SIZE};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use std::io::Write;
+
+/// The session id the challenge 51 requests carry.
+pub const CRIME_SESSION_ID: &str = "TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+// Filler for the attack, none of these appear in base64 or in the request
+// headers so the compressor can only encode them as literals.
+const FILLER_ALPHABET: &[u8] = b"!#$%&*<>?@[]^_{|}~`";
+const FILLER_CROSSINGS: usize = 6;
+const MATCH_WINDOW: usize = 19;
+const STEP_ATTEMPTS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompressionCipher {
+    Ctr,
+    Cbc,
+}
+
+pub fn format_request(body: &[u8], session_id: &str) -> Vec<u8> {
+    let mut request = format!(
+        "POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid={}\nContent-Length: {}\n",
+        session_id,
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// An oracle that puts the attacker's body into a request carrying the
+/// session cookie, compresses and encrypts it under a fresh key and returns
+/// only the length of the ciphertext, like someone watching the wire.
+pub fn compression_oracle(session_id: &str, cipher: CompressionCipher) -> impl Fn(&[u8]) -> usize {
+    let session_id = session_id.to_string();
+    move |body: &[u8]| {
+        let compressed = deflate(&format_request(body, &session_id));
+        let mut rng = thread_rng();
+        let key: [u8; AES_BLOCK_SIZE] = rng.gen();
+        match cipher {
+            CompressionCipher::Ctr => aes_ctr(&compressed, &key, rng.gen()).len(),
+            CompressionCipher::Cbc => {
+                let iv: [u8; AES_BLOCK_SIZE] = rng.gen();
+                encrypt_aes_cbc(&compressed, &key.to_vec(), &iv.to_vec()).len()
+            }
+        }
+    }
+}
+
+// Filler lengths that leave the compressed guess right below a ciphertext
+// length boundary. The length only changes every byte (CTR) or every block
+// (CBC), at these offsets a few more bits from a wrong guess tick it over.
+fn boundary_fillers(
+    oracle: &impl Fn(&[u8]) -> usize,
+    guess: &[u8],
+    filler: &[u8],
+) -> Result<Vec<usize>, &'static str> {
+    let mut fillers: Vec<usize> = Vec::new();
+    let mut previous = oracle(guess);
+    let mut body = Vec::with_capacity(filler.len() + guess.len());
+    for len in 1..=filler.len() {
+        body.clear();
+        body.extend_from_slice(&filler[..len]);
+        body.extend_from_slice(guess);
+        let current = oracle(&body);
+        if current > previous {
+            fillers.push(len - 1);
+            if fillers.len() == FILLER_CROSSINGS {
+                return Ok(fillers);
+            }
+        }
+        previous = current;
+    }
+    Err("Filler is too short to reach enough length boundaries")
+}
+
+// Pick the candidate that compresses best after `guess`, with `filler`
+// used to move the request onto length boundaries.
+fn best_candidate(
+    oracle: &impl Fn(&[u8]) -> usize,
+    guess: &[u8],
+    filler: &[u8],
+    candidates: &[u8],
+) -> Result<u8, &'static str> {
+    let fillers = boundary_fillers(oracle, guess, filler)?;
+    let score = |suffix: &[u8]| -> usize {
+        fillers
+            .iter()
+            .map(|len| {
+                let mut body = filler[..*len].to_vec();
+                body.extend_from_slice(guess);
+                body.extend_from_slice(suffix);
+                oracle(&body)
+            })
+            .sum()
+    };
+
+    let scores: Vec<usize> = candidates.iter().map(|c| score(&[*c])).collect();
+    let best = *scores.iter().min().unwrap();
+    let mut tied: Vec<u8> = candidates
+        .iter()
+        .zip(scores.iter())
+        .filter(|(_, s)| **s == best)
+        .map(|(c, _)| *c)
+        .collect();
+    if tied.len() > 1 {
+        let lookahead: Vec<usize> = tied
+            .iter()
+            .map(|c| candidates.iter().map(|d| score(&[*c, *d])).min().unwrap())
+            .collect();
+        let best = *lookahead.iter().min().unwrap();
+        tied = tied
+            .into_iter()
+            .zip(lookahead)
+            .filter(|(_, s)| *s == best)
+            .map(|(c, _)| c)
+            .collect();
+    }
+    if tied.len() > 1 {
+        return Err("Could not tell candidates apart");
+    }
+    Ok(tied[0])
+}
+
+/// Recover a secret that follows `known_prefix` in the request from
+/// ciphertext lengths alone. Each step appends every candidate byte to what
+/// is known so far and keeps the one that compresses best, the right guess
+/// extends an LZ77 match instead of adding a literal. Candidates are scored
+/// by the total length over several filler lengths that sit right at a
+/// length boundary, which works for the coarse lengths of CBC as well, and
+/// ties are broken by looking one byte further ahead. A step that still
+/// ends in a tie is retried with fresh random filler. Stops once
+/// `terminator` is the best guess.
+///
+/// Guesses only repeat the last 19 known bytes: DEFLATE codes match lengths
+/// 19 to 22 with the same symbol, so the right guess never changes the
+/// length code and wins by the full cost of a literal. The known prefix has
+/// to be at least that long, e.g. `\nCookie: sessionid=`.
+pub fn compression_ratio_attack(
+    oracle: impl Fn(&[u8]) -> usize,
+    known_prefix: &[u8],
+    alphabet: &[u8],
+    terminator: u8,
+    max_len: usize,
+) -> Result<Vec<u8>, &'static str> {
+    if known_prefix.len() < MATCH_WINDOW {
+        return Err("Known prefix is too short");
+    }
+    let mut rng = thread_rng();
+    let mut candidates = alphabet.to_vec();
+    candidates.push(terminator);
+
+    let mut known = known_prefix.to_vec();
+    while known.len() - known_prefix.len() < max_len {
+        let guess = &known[known.len() - MATCH_WINDOW..];
+        let mut next = Err("Could not tell candidates apart");
+        for _ in 0..STEP_ATTEMPTS {
+            let filler: Vec<u8> = (0..32 * AES_BLOCK_SIZE)
+                .map(|_| *FILLER_ALPHABET.choose(&mut rng).unwrap())
+                .collect();
+            next = best_candidate(&oracle, guess, &filler, &candidates);
+            if next.is_ok() {
+                break;
+            }
+        }
+        let next = next?;
+        if next == terminator {
+            return Ok(known[known_prefix.len()..].to_vec());
+        }
+        known.push(next);
+    }
+    Err("Secret is longer than the maximum length")
+}
@@ -0,0 +1,186 @@
+use crate::bigint::{
+    bigint_to_bytes, chinese_remainder, mod_exp, random_range, trial_division, BigUint,
+};
+use crate::dlog::{DiscreteLogSolver, Group, MultiplicativeGroup};
+use crate::hmac::hmac_sha256;
+use num_traits::{One, Zero};
+
+const CHALLENGE_57_P: &str = "7199773997391911030609999317773941274322764333428698921736339643928346453700085358802973900485592910475480089726140708102474957429903531369589969318716771";
+const CHALLENGE_57_G: &str = "4565356397095740655436854503483826832136106141639563487732438195343690437606117828318042418238184896212352329118608100083187535033402010599512641674644143";
+const CHALLENGE_57_Q: &str = "236234353446506858198510045061214171961";
+const CHALLENGE_58_P: &str = "11470374874925275658116663507232161402086650258453896274534991676898999262641581519101074740642369848233294239851519212341844337347119899874391456329785623";
+const CHALLENGE_58_G: &str = "622952335333961296978159266084741085889881358738459939978290179936063635566740258555167783009058567397963466103140082647486611657350811560630587013183357";
+const CHALLENGE_58_Q: &str = "335062023296420808191071248367701059461";
+
+/// The message the victim authenticates with the shared secret.
+pub const DH_VICTIM_MESSAGE: &[u8] = b"crazy flamboyant for the rap enjoyment";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DhParameters {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+}
+
+impl DhParameters {
+    /// A 512 bit p where g generates a subgroup of prime order q, with
+    /// plenty of small factors left in (p - 1) / q.
+    pub fn challenge_57() -> DhParameters {
+        DhParameters {
+            p: BigUint::parse_bytes(CHALLENGE_57_P.as_bytes(), 10).unwrap(),
+            q: BigUint::parse_bytes(CHALLENGE_57_Q.as_bytes(), 10).unwrap(),
+            g: BigUint::parse_bytes(CHALLENGE_57_G.as_bytes(), 10).unwrap(),
+        }
+    }
+
+    /// Like challenge_57 but the small factors of (p - 1) / q only add up to
+    /// about 89 of the 128 bits of q.
+    pub fn challenge_58() -> DhParameters {
+        DhParameters {
+            p: BigUint::parse_bytes(CHALLENGE_58_P.as_bytes(), 10).unwrap(),
+            q: BigUint::parse_bytes(CHALLENGE_58_Q.as_bytes(), 10).unwrap(),
+            g: BigUint::parse_bytes(CHALLENGE_58_G.as_bytes(), 10).unwrap(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DhPublicKey {
+    pub params: DhParameters,
+    pub y: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DhPrivateKey {
+    pub params: DhParameters,
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+impl DhPrivateKey {
+    pub fn public_key(&self) -> DhPublicKey {
+        DhPublicKey {
+            params: self.params.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+pub fn generate_dh_keys(params: &DhParameters) -> (DhPublicKey, DhPrivateKey) {
+    let x = random_range(&BigUint::one(), &params.q);
+    let y = mod_exp(&params.g, &x, &params.p);
+    let private = DhPrivateKey {
+        params: params.clone(),
+        x,
+        y,
+    };
+    (private.public_key(), private)
+}
+
+/// The shared secret other^x mod p. The other party's key is used as is.
+pub fn dh_shared_secret(key: &DhPrivateKey, other: &BigUint) -> BigUint {
+    mod_exp(other, &key.x, &key.params.p)
+}
+
+/// A random element of order r in Z_p*, r has to be a prime dividing p - 1.
+pub fn element_of_order(p: &BigUint, r: u64) -> Result<BigUint, &'static str> {
+    let p_minus_one = p - 1u32;
+    if r < 2 || !(&p_minus_one % r).is_zero() {
+        return Err("Order does not divide p - 1");
+    }
+    let cofactor = &p_minus_one / r;
+    loop {
+        let h = mod_exp(&random_range(&BigUint::one(), p), &cofactor, p);
+        if !h.is_one() {
+            return Ok(h);
+        }
+    }
+}
+
+/// A victim that takes any public key, derives the shared secret and
+/// answers with a message and its HMAC-SHA256 under that secret.
+pub fn dh_mac_victim(key: &DhPrivateKey) -> impl Fn(&BigUint) -> (Vec<u8>, Vec<u8>) {
+    let key = key.clone();
+    move |public: &BigUint| {
+        let secret = dh_shared_secret(&key, public);
+        let mac = hmac_sha256(&bigint_to_bytes(&secret), DH_VICTIM_MESSAGE);
+        (DH_VICTIM_MESSAGE.to_vec(), mac)
+    }
+}
+
+// Find k in [0, r) with MAC(h^k, message) == mac.
+fn brute_force_mac(p: &BigUint, h: &BigUint, r: u64, message: &[u8], mac: &[u8]) -> Option<u64> {
+    let mut secret = BigUint::one();
+    for k in 0..r {
+        if hmac_sha256(&bigint_to_bytes(&secret), message) == mac {
+            return Some(k);
+        }
+        secret = secret * h % p;
+    }
+    None
+}
+
+/// Small subgroup confinement. Send the victim elements h of small prime
+/// order r dividing (p - 1) / q, the shared secret h^x then only has r
+/// possible values and brute forcing the MAC gives x mod r. Factors below
+/// `bound` are used until their product exceeds q, the residues are
+/// combined with the CRT. Returns (x mod m, m), when m is still smaller
+/// than q the caller has to find the rest of x some other way.
+pub fn subgroup_confinement_attack(
+    params: &DhParameters,
+    victim: impl Fn(&BigUint) -> (Vec<u8>, Vec<u8>),
+    bound: u64,
+) -> Result<(BigUint, BigUint), &'static str> {
+    let (factors, _) = trial_division(&((&params.p - 1u32) / &params.q), bound);
+    let mut residues = Vec::new();
+    let mut moduli = Vec::new();
+    let mut modulus = BigUint::one();
+    for (r, _) in factors {
+        let h = element_of_order(&params.p, r)?;
+        let (message, mac) = victim(&h);
+        let residue = brute_force_mac(&params.p, &h, r, &message, &mac)
+            .ok_or("No residue matches the MAC")?;
+        residues.push(BigUint::from(residue));
+        moduli.push(BigUint::from(r));
+        modulus *= r;
+        if modulus > params.q {
+            break;
+        }
+    }
+    if moduli.is_empty() {
+        return Err("No small factors below the bound");
+    }
+    chinese_remainder(&residues, &moduli)
+}
+
+/// Recover the victim's private key when the small factors do not cover q.
+/// With x = n mod r from the subgroup confinement attack x = n + m r and
+/// y g^-n = (g^r)^m, so m is a discrete log in [0, (q - 1) / r] that the
+/// solver can take care of.
+pub fn recover_dh_private_key(
+    key: &DhPublicKey,
+    victim: impl Fn(&BigUint) -> (Vec<u8>, Vec<u8>),
+    bound: u64,
+    solver: &impl DiscreteLogSolver,
+) -> Result<BigUint, &'static str> {
+    let params = &key.params;
+    let (n, r) = subgroup_confinement_attack(params, victim, bound)?;
+    if r > params.q {
+        return Ok(n);
+    }
+    let group = MultiplicativeGroup {
+        p: params.p.clone(),
+    };
+    let base = group.power(&params.g, &r);
+    let target = group.combine(&key.y, &group.inverse(&group.power(&params.g, &n)));
+    let m = solver
+        .solve(
+            &group,
+            &base,
+            &target,
+            &BigUint::zero(),
+            &((&params.q - 1u32) / &r),
+        )
+        .ok_or("Discrete log not found in the remaining range")?;
+    Ok(n + m * r)
+}
@@ -0,0 +1,225 @@
+use crate::bigint::{chinese_remainder, mod_exp, mod_inverse, BigUint};
+use num_traits::{One, ToPrimitive, Zero};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A cyclic group written multiplicatively, whatever the discrete log
+/// solvers need to walk around in it.
+pub trait Group {
+    type Element: Clone + Eq + Hash;
+
+    fn identity(&self) -> Self::Element;
+    fn combine(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    fn inverse(&self, a: &Self::Element) -> Self::Element;
+    fn power(&self, base: &Self::Element, exp: &BigUint) -> Self::Element;
+    /// Some bits derived from the element, used to pick pseudo-random jumps.
+    fn fingerprint(&self, a: &Self::Element) -> u64;
+}
+
+/// The integers modulo a prime p under multiplication.
+pub struct MultiplicativeGroup {
+    pub p: BigUint,
+}
+
+impl Group for MultiplicativeGroup {
+    type Element = BigUint;
+
+    fn identity(&self) -> BigUint {
+        BigUint::one()
+    }
+
+    fn combine(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b % &self.p
+    }
+
+    fn inverse(&self, a: &BigUint) -> BigUint {
+        mod_inverse(a, &self.p).unwrap()
+    }
+
+    fn power(&self, base: &BigUint, exp: &BigUint) -> BigUint {
+        mod_exp(base, exp, &self.p)
+    }
+
+    fn fingerprint(&self, a: &BigUint) -> u64 {
+        a.iter_u64_digits().next().unwrap_or(0)
+    }
+}
+
+/// Anything that finds x in [low, high] with base^x = target.
+pub trait DiscreteLogSolver {
+    fn solve<G: Group>(
+        &self,
+        group: &G,
+        base: &G::Element,
+        target: &G::Element,
+        low: &BigUint,
+        high: &BigUint,
+    ) -> Option<BigUint>;
+}
+
+/// Shanks' baby-step giant-step. Deterministic and takes sqrt(high - low)
+/// steps, but also needs a table of that many elements.
+pub struct BabyStepGiantStep;
+
+impl DiscreteLogSolver for BabyStepGiantStep {
+    fn solve<G: Group>(
+        &self,
+        group: &G,
+        base: &G::Element,
+        target: &G::Element,
+        low: &BigUint,
+        high: &BigUint,
+    ) -> Option<BigUint> {
+        if high < low {
+            return None;
+        }
+        let width = high - low + 1u32;
+        let m = (&width - 1u32).sqrt() + 1u32;
+        let m_steps = m.to_u64()?;
+
+        let mut baby_steps = HashMap::new();
+        let mut element = group.identity();
+        for j in 0..m_steps {
+            baby_steps.entry(element.clone()).or_insert(j);
+            element = group.combine(&element, base);
+        }
+
+        // target * base^-low * base^(-m i) for increasing i
+        let giant_step = group.inverse(&group.power(base, &m));
+        let mut element = group.combine(target, &group.inverse(&group.power(base, low)));
+        for i in 0..m_steps {
+            if let Some(j) = baby_steps.get(&element) {
+                let x = low + &m * i + j;
+                return if &x <= high { Some(x) } else { None };
+            }
+            element = group.combine(&element, &giant_step);
+        }
+        None
+    }
+}
+
+const KANGAROO_ATTEMPTS: u64 = 4;
+
+/// Pollard's kangaroo (lambda) algorithm. A tame kangaroo hops from base^high
+/// and sets a trap where it stops, then a wild kangaroo hops from the
+/// target. The jumps only depend on the current element, so once the wild
+/// kangaroo lands on a spot the tame one visited it follows it into the
+/// trap. Needs about sqrt(high - low) steps and constant memory.
+///
+/// Jumps are powers of two below 2^k. By default k gives a mean jump of
+/// about sqrt(width) / 2 and the tame kangaroo makes 8 times the mean jump.
+/// A miss restarts both with shifted jumps, up to `KANGAROO_ATTEMPTS` times.
+#[derive(Default)]
+pub struct Kangaroo {
+    parameters: Option<(u32, u64)>,
+}
+
+impl Kangaroo {
+    pub fn new() -> Kangaroo {
+        Kangaroo { parameters: None }
+    }
+
+    pub fn with_parameters(k: u32, tame_jumps: u64) -> Kangaroo {
+        Kangaroo {
+            parameters: Some((k, tame_jumps)),
+        }
+    }
+
+    fn parameters(&self, width: &BigUint) -> (u32, u64) {
+        if let Some(parameters) = self.parameters {
+            return parameters;
+        }
+        let mean_jump = (width.sqrt() / 2u32).to_u64().unwrap_or(u64::MAX);
+        let mut k = 1u32;
+        while k < 62 && (1u64 << k) / u64::from(k) < mean_jump {
+            k += 1;
+        }
+        (k, 8 * ((1u64 << k) / u64::from(k)))
+    }
+}
+
+impl DiscreteLogSolver for Kangaroo {
+    fn solve<G: Group>(
+        &self,
+        group: &G,
+        base: &G::Element,
+        target: &G::Element,
+        low: &BigUint,
+        high: &BigUint,
+    ) -> Option<BigUint> {
+        if high < low {
+            return None;
+        }
+        let (k, tame_jumps) = self.parameters(&(high - low));
+        let k = u64::from(k.clamp(1, 63));
+        let jumps: Vec<(u64, G::Element)> = (0..k)
+            .map(|i| (1u64 << i, group.power(base, &BigUint::from(1u64 << i))))
+            .collect();
+        let tame_start = group.power(base, high);
+
+        // Every attempt shifts which element takes which jump, so the walks
+        // of a missed attempt are not repeated.
+        for attempt in 0..KANGAROO_ATTEMPTS {
+            let jump = |element: &G::Element| {
+                &jumps[(group.fingerprint(element).wrapping_add(attempt) % k) as usize]
+            };
+            let mut tame_distance = BigUint::zero();
+            let mut tame = tame_start.clone();
+            for _ in 0..tame_jumps {
+                let (distance, step) = jump(&tame);
+                tame_distance += *distance;
+                tame = group.combine(&tame, step);
+            }
+
+            // The wild kangaroo has passed the trap once it went further than
+            // the tame one from anywhere in the interval.
+            let limit = high - low + &tame_distance;
+            let mut wild_distance = BigUint::zero();
+            let mut wild = target.clone();
+            while wild_distance <= limit {
+                if wild == tame {
+                    let end = high + &tame_distance;
+                    return (end >= wild_distance)
+                        .then(|| end - wild_distance)
+                        .filter(|x| x >= low && x <= high);
+                }
+                let (distance, step) = jump(&wild);
+                wild_distance += *distance;
+                wild = group.combine(&wild, step);
+            }
+        }
+        None
+    }
+}
+
+/// Pohlig-Hellman for a base whose order is the product of the distinct
+/// primes in `factors`. Projecting onto the subgroup of each prime order r
+/// leaves a discrete log below r for the solver, the CRT puts the pieces
+/// together into x mod the order.
+pub fn pohlig_hellman<G: Group>(
+    group: &G,
+    base: &G::Element,
+    target: &G::Element,
+    factors: &[u64],
+    solver: &impl DiscreteLogSolver,
+) -> Option<BigUint> {
+    let order: BigUint = factors.iter().map(|r| BigUint::from(*r)).product();
+    let mut residues = Vec::new();
+    let mut moduli = Vec::new();
+    for r in factors {
+        let cofactor = &order / *r;
+        let projected_base = group.power(base, &cofactor);
+        let projected_target = group.power(target, &cofactor);
+        let high = BigUint::from(r - 1);
+        let residue = solver.solve(
+            group,
+            &projected_base,
+            &projected_target,
+            &BigUint::zero(),
+            &high,
+        )?;
+        residues.push(residue);
+        moduli.push(BigUint::from(*r));
+    }
+    chinese_remainder(&residues, &moduli).ok().map(|(x, _)| x)
+}
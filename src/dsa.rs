@@ -0,0 +1,312 @@
+use crate::bigint::{
+    bytes_to_bigint, is_probable_prime, mod_exp, mod_inverse, modulo, random_range, BigInt, BigUint,
+};
+use crate::sha1::sha1;
+use num_traits::{One, Zero};
+use std::collections::HashMap;
+use std::ops::Range;
+
+const CRYPTOPALS_P: &str = "800000000000000089e1855218a0e7dac38136ffafa72eda7859f2171e25e65eac698c1702578b07dc2a1076da241c76c62d374d8389ea5aeffd3226a0530cc565f3bf6b50929139ebeac04f48c3c84afb796d61e5a4f9a8fda812ab59494232c7d2b4deb50aa18ee9e132bfa85ac4374d7f9091abc3d015efc871a584471bb1";
+const CRYPTOPALS_Q: &str = "f4f47f05794b256174bba6e9b396a7707e563c5b";
+const CRYPTOPALS_G: &str = "5958c9d3898b224b12672c0b98e06c60df923cb8bc999d119458fef538b8fa4046c8db53039db620c094c9fa077ef389b5322a559946a71903f990f1f7e0e025e2d7f7cf494aff1a0470f5b64c36b625a097f1651fe775323556fe00b3608c887892878480e99041be601a62166ca6894bdd41a7054ec89f756ba9fc95302291";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsaParameters {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+}
+
+impl DsaParameters {
+    /// The 1024 bit p, 160 bit q domain parameters used throughout Set 6.
+    pub fn cryptopals() -> DsaParameters {
+        DsaParameters {
+            p: BigUint::parse_bytes(CRYPTOPALS_P.as_bytes(), 16).unwrap(),
+            q: BigUint::parse_bytes(CRYPTOPALS_Q.as_bytes(), 16).unwrap(),
+            g: BigUint::parse_bytes(CRYPTOPALS_G.as_bytes(), 16).unwrap(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsaPublicKey {
+    pub params: DsaParameters,
+    pub y: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsaPrivateKey {
+    pub params: DsaParameters,
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+impl DsaPrivateKey {
+    pub fn public_key(&self) -> DsaPublicKey {
+        DsaPublicKey {
+            params: self.params.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsaSignature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+pub fn generate_dsa_keys(params: &DsaParameters) -> (DsaPublicKey, DsaPrivateKey) {
+    let x = random_range(&BigUint::one(), &params.q);
+    let y = mod_exp(&params.g, &x, &params.p);
+    let private = DsaPrivateKey {
+        params: params.clone(),
+        x,
+        y,
+    };
+    (private.public_key(), private)
+}
+
+/// SHA-1 of the message interpreted as an integer.
+pub fn dsa_hash(message: &[u8]) -> BigUint {
+    bytes_to_bigint(&sha1(message))
+}
+
+/// Sign with a caller chosen nonce k. Only useful to show what happens
+/// when the nonce is predictable, use dsa_sign otherwise.
+pub fn dsa_sign_with_nonce(
+    message: &[u8],
+    key: &DsaPrivateKey,
+    k: &BigUint,
+) -> Result<DsaSignature, &'static str> {
+    let DsaParameters { p, q, g } = &key.params;
+    let r = mod_exp(g, k, p) % q;
+    let k_inverse = mod_inverse(k, q)?;
+    let s = (k_inverse * (dsa_hash(message) + &key.x * &r)) % q;
+    if r.is_zero() || s.is_zero() {
+        return Err("Nonce produced a zero signature component");
+    }
+    Ok(DsaSignature { r, s })
+}
+
+pub fn dsa_sign(message: &[u8], key: &DsaPrivateKey) -> DsaSignature {
+    loop {
+        let k = random_range(&BigUint::one(), &key.params.q);
+        if let Ok(signature) = dsa_sign_with_nonce(message, key, &k) {
+            return signature;
+        }
+    }
+}
+
+/// DSA verification including the 0 < r < q and 0 < s < q range checks.
+pub fn dsa_verify(message: &[u8], signature: &DsaSignature, key: &DsaPublicKey) -> bool {
+    let DsaParameters { p, q, g } = &key.params;
+    let DsaSignature { r, s } = signature;
+    if r.is_zero() || s.is_zero() || r >= q || s >= q {
+        return false;
+    }
+    let w = match mod_inverse(s, q) {
+        Ok(w) => w,
+        Err(_) => return false,
+    };
+    let u1 = (dsa_hash(message) * &w) % q;
+    let u2 = (r * &w) % q;
+    let v = ((mod_exp(g, &u1, p) * mod_exp(&key.y, &u2, p)) % p) % q;
+    v == *r
+}
+
+/// Recover the private key from a signature whose nonce is known:
+/// x = (s * k - H(m)) / r mod q.
+pub fn dsa_private_key_from_nonce(
+    hash: &BigUint,
+    signature: &DsaSignature,
+    k: &BigUint,
+    q: &BigUint,
+) -> Result<BigUint, &'static str> {
+    let r_inverse = mod_inverse(&signature.r, q)?;
+    let numerator = BigInt::from(&signature.s * k) - BigInt::from(hash.clone());
+    Ok((modulo(&numerator, q) * r_inverse) % q)
+}
+
+// CryptoPals Set 6 Challenge 43
+/// Recover the private key of a signature whose nonce lies in the given
+/// range. Every candidate k is checked against r = (g^k mod p) mod q,
+/// stepping g^k by one multiplication at a time, before deriving x.
+pub fn recover_dsa_key_from_nonce_range(
+    hash: &BigUint,
+    signature: &DsaSignature,
+    key: &DsaPublicKey,
+    nonces: Range<u64>,
+) -> Option<BigUint> {
+    let DsaParameters { p, q, g } = &key.params;
+    let mut g_k = mod_exp(g, &BigUint::from(nonces.start), p);
+    for k in nonces {
+        if (&g_k % q) == signature.r {
+            let k = BigUint::from(k);
+            if let Ok(x) = dsa_private_key_from_nonce(hash, signature, &k, q) {
+                if mod_exp(g, &x, p) == key.y {
+                    return Some(x);
+                }
+            }
+        }
+        g_k = (g_k * g) % p;
+    }
+    None
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsaSignedMessage {
+    pub message: String,
+    pub hash: BigUint,
+    pub signature: DsaSignature,
+}
+
+/// Parse a corpus of signed messages made of groups of four lines:
+/// "msg: <message>", "s: <decimal>", "r: <decimal>" and "m: <hex SHA-1>".
+pub fn load_dsa_signed_messages(contents: &str) -> Result<Vec<DsaSignedMessage>, String> {
+    let lines: Vec<&str> = contents.lines().filter(|x| !x.is_empty()).collect();
+    if !lines.len().is_multiple_of(4) {
+        return Err("Corpus is not made of groups of four lines".to_string());
+    }
+
+    let field = |line: &str, name: &str| -> Result<String, String> {
+        line.strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix(": "))
+            .map(|value| value.to_string())
+            .ok_or(format!("Expected field {} in line {:?}", name, line))
+    };
+    let integer = |value: String, radix: u32| -> Result<BigUint, String> {
+        BigUint::parse_bytes(value.trim().as_bytes(), radix)
+            .ok_or(format!("Invalid number {:?}", value))
+    };
+
+    let mut messages: Vec<DsaSignedMessage> = Vec::new();
+    for group in lines.chunks(4) {
+        messages.push(DsaSignedMessage {
+            message: field(group[0], "msg")?,
+            signature: DsaSignature {
+                s: integer(field(group[1], "s")?, 10)?,
+                r: integer(field(group[2], "r")?, 10)?,
+            },
+            hash: integer(field(group[3], "m")?, 16)?,
+        });
+    }
+    Ok(messages)
+}
+
+// CryptoPals Set 6 Challenge 44
+/// Find every pair of signatures sharing a nonce, recognisable by an equal
+/// r, and recover the nonce as k = (m1 - m2) / (s1 - s2) mod q and from it
+/// the private key. Candidate keys are matched against the given public
+/// keys, so a corpus mixing several signers returns every compromised key.
+pub fn recover_dsa_keys_from_repeated_nonces(
+    messages: &[DsaSignedMessage],
+    public_keys: &[DsaPublicKey],
+) -> Vec<DsaPrivateKey> {
+    let mut by_r: HashMap<&BigUint, Vec<&DsaSignedMessage>> = HashMap::new();
+    for message in messages.iter() {
+        by_r.entry(&message.signature.r).or_default().push(message);
+    }
+
+    let mut compromised: Vec<DsaPrivateKey> = Vec::new();
+    for group in by_r.values().filter(|group| group.len() > 1) {
+        for (i, first) in group.iter().enumerate() {
+            for second in group.iter().skip(i + 1) {
+                for key in public_keys.iter() {
+                    if compromised.iter().any(|found| found.y == key.y) {
+                        continue;
+                    }
+                    let q = &key.params.q;
+                    let hash_diff =
+                        BigInt::from(first.hash.clone()) - BigInt::from(second.hash.clone());
+                    let s_diff = BigInt::from(first.signature.s.clone())
+                        - BigInt::from(second.signature.s.clone());
+                    let s_diff_inverse = match mod_inverse(&modulo(&s_diff, q), q) {
+                        Ok(inverse) => inverse,
+                        Err(_) => continue,
+                    };
+                    let k = (modulo(&hash_diff, q) * s_diff_inverse) % q;
+                    if let Ok(x) = dsa_private_key_from_nonce(&first.hash, &first.signature, &k, q)
+                    {
+                        if mod_exp(&key.params.g, &x, &key.params.p) == key.y {
+                            compromised.push(DsaPrivateKey {
+                                params: key.params.clone(),
+                                x,
+                                y: key.y.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    compromised
+}
+
+/// A verifier that trusts the domain parameters it is handed and skips the
+/// range checks on r and s, as plenty of hand rolled implementations do.
+pub fn dsa_verify_unchecked(message: &[u8], signature: &DsaSignature, key: &DsaPublicKey) -> bool {
+    let DsaParameters { p, q, g } = &key.params;
+    let DsaSignature { r, s } = signature;
+    let w = match mod_inverse(&(s % q), q) {
+        Ok(w) => w,
+        Err(_) => return false,
+    };
+    let u1 = (dsa_hash(message) * &w) % q;
+    let u2 = (r * &w) % q;
+    let v = ((mod_exp(g, &u1, p) * mod_exp(&key.y, &u2, p)) % p) % q;
+    v == *r
+}
+
+/// Check that domain parameters and public key are well formed: p and q
+/// prime, q dividing p - 1, g generating the subgroup of order q and y a
+/// member of that subgroup.
+pub fn validate_dsa_public_key(key: &DsaPublicKey) -> Result<(), &'static str> {
+    let DsaParameters { p, q, g } = &key.params;
+    let one = BigUint::one();
+    if !is_probable_prime(p, 40) || !is_probable_prime(q, 40) {
+        return Err("p and q have to be prime");
+    }
+    if !((p - &one) % q).is_zero() {
+        return Err("q has to divide p - 1");
+    }
+    if *g <= one || *g >= *p || !mod_exp(g, q, p).is_one() {
+        return Err("g does not generate the subgroup of order q");
+    }
+    if key.y <= one || key.y >= *p || !mod_exp(&key.y, q, p).is_one() {
+        return Err("y is not in the subgroup of order q");
+    }
+    Ok(())
+}
+
+/// Verification that rejects tampered domain parameters before checking
+/// the signature itself.
+pub fn dsa_verify_strict(message: &[u8], signature: &DsaSignature, key: &DsaPublicKey) -> bool {
+    validate_dsa_public_key(key).is_ok() && dsa_verify(message, signature, key)
+}
+
+// CryptoPals Set 6 Challenge 45
+/// With g = 0 every signature has r = 0, and a verifier that does not
+/// check 0 < r accepts r = 0 with any s for every message.
+pub fn dsa_zero_generator_signature(params: &DsaParameters) -> DsaSignature {
+    DsaSignature {
+        r: BigUint::zero(),
+        s: random_range(&BigUint::one(), &params.q),
+    }
+}
+
+/// With g = p + 1 the generator term of the verification equation is
+/// always 1. Picking any z, r = (y^z mod p) mod q and s = r / z mod q is
+/// then a signature that verifies for every message.
+pub fn dsa_magic_signature(key: &DsaPublicKey) -> DsaSignature {
+    let DsaParameters { p, q, .. } = &key.params;
+    loop {
+        let z = random_range(&BigUint::one(), q);
+        let r = mod_exp(&key.y, &z, p) % q;
+        let z_inverse = mod_inverse(&z, q).unwrap();
+        let s = (&r * z_inverse) % q;
+        if !r.is_zero() && !s.is_zero() {
+            return DsaSignature { r, s };
+        }
+    }
+}
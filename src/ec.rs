@@ -0,0 +1,724 @@
+use crate::bigint::{
+    bigint_to_bytes, bytes_to_bigint, chinese_remainder, mod_inverse, mod_sqrt, random_range,
+    trial_division, BigUint,
+};
+use crate::dlog::{DiscreteLogSolver, Group};
+use crate::hmac::hmac_sha256;
+use num_traits::{One, Zero};
+use openssl::sha::sha256;
+
+const CRYPTOPALS_P: &str = "233970423115425145524320034830162017933";
+const CRYPTOPALS_B: &str = "11279326";
+const CRYPTOPALS_GX: &str = "182";
+const CRYPTOPALS_GY: &str = "85518893674295321206118380980485522083";
+const CRYPTOPALS_N: &str = "29246302889428143187362802287225875743";
+const CRYPTOPALS_MONTGOMERY_A: u32 = 534;
+const CRYPTOPALS_MONTGOMERY_U: u32 = 4;
+
+const P256_P: &str = "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff";
+const P256_B: &str = "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b";
+const P256_GX: &str = "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296";
+const P256_GY: &str = "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5";
+const P256_N: &str = "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+
+const SECP256K1_P: &str = "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f";
+const SECP256K1_GX: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const SECP256K1_GY: &str = "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+const SECP256K1_N: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+/// Curves that only differ from the Cryptopals curve in b, with their
+/// orders. The group law never looks at b, so points on them are happily
+/// multiplied by code that thinks it works on the real curve.
+pub const INVALID_CURVES: [(u32, &str); 3] = [
+    (210, "233970423115425145550826547352470124412"),
+    (504, "233970423115425145544350131142039591210"),
+    (727, "233970423115425145545378039958152057148"),
+];
+
+/// The message the ECDH victim authenticates with the shared secret.
+pub const ECDH_VICTIM_MESSAGE: &[u8] = b"crazy flamboyant for the rap enjoyment";
+
+fn parse(number: &str, radix: u32) -> BigUint {
+    BigUint::parse_bytes(number.as_bytes(), radix).unwrap()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EcPoint {
+    Infinity,
+    Affine { x: BigUint, y: BigUint },
+}
+
+impl EcPoint {
+    pub fn new(x: BigUint, y: BigUint) -> EcPoint {
+        EcPoint::Affine { x, y }
+    }
+
+    /// x || y, or nothing for the point at infinity.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            EcPoint::Infinity => Vec::new(),
+            EcPoint::Affine { x, y } => [bigint_to_bytes(x), bigint_to_bytes(y)].concat(),
+        }
+    }
+}
+
+/// A short Weierstrass curve y^2 = x^3 + ax + b over the prime field F_p
+/// together with a base point g of prime order n.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeierstrassCurve {
+    pub p: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    pub g: EcPoint,
+    pub n: BigUint,
+}
+
+impl WeierstrassCurve {
+    /// y^2 = x^3 - 95051x + 11279326 from Set 8, its order is 8n.
+    pub fn cryptopals() -> WeierstrassCurve {
+        let p = parse(CRYPTOPALS_P, 10);
+        WeierstrassCurve {
+            a: &p - 95051u32,
+            b: parse(CRYPTOPALS_B, 10),
+            g: EcPoint::new(parse(CRYPTOPALS_GX, 10), parse(CRYPTOPALS_GY, 10)),
+            n: parse(CRYPTOPALS_N, 10),
+            p,
+        }
+    }
+
+    /// NIST P-256 (secp256r1).
+    pub fn p256() -> WeierstrassCurve {
+        let p = parse(P256_P, 16);
+        WeierstrassCurve {
+            a: &p - 3u32,
+            b: parse(P256_B, 16),
+            g: EcPoint::new(parse(P256_GX, 16), parse(P256_GY, 16)),
+            n: parse(P256_N, 16),
+            p,
+        }
+    }
+
+    /// secp256k1, the Bitcoin curve y^2 = x^3 + 7.
+    pub fn secp256k1() -> WeierstrassCurve {
+        WeierstrassCurve {
+            p: parse(SECP256K1_P, 16),
+            a: BigUint::zero(),
+            b: BigUint::from(7u32),
+            g: EcPoint::new(parse(SECP256K1_GX, 16), parse(SECP256K1_GY, 16)),
+            n: parse(SECP256K1_N, 16),
+        }
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + &self.p - b % &self.p) % &self.p
+    }
+
+    pub fn is_on_curve(&self, point: &EcPoint) -> bool {
+        match point {
+            EcPoint::Infinity => true,
+            EcPoint::Affine { x, y } => {
+                x < &self.p
+                    && y < &self.p
+                    && (y * y) % &self.p == (x * x * x + &self.a * x + &self.b) % &self.p
+            }
+        }
+    }
+
+    /// A uniformly random point other than infinity, a random x until
+    /// x^3 + ax + b has a square root.
+    pub fn random_point(&self) -> EcPoint {
+        loop {
+            let x = random_range(&BigUint::zero(), &self.p);
+            let rhs = (&x * &x * &x + &self.a * &x + &self.b) % &self.p;
+            if let Some(y) = mod_sqrt(&rhs, &self.p) {
+                return EcPoint::new(x, y);
+            }
+        }
+    }
+
+    pub fn negate(&self, point: &EcPoint) -> EcPoint {
+        match point {
+            EcPoint::Infinity => EcPoint::Infinity,
+            EcPoint::Affine { x, y } => EcPoint::new(x.clone(), self.sub(&BigUint::zero(), y)),
+        }
+    }
+
+    /// The group law, the chord through both points or the tangent when
+    /// they are the same.
+    pub fn add(&self, first: &EcPoint, second: &EcPoint) -> EcPoint {
+        let (x1, y1, x2, y2) = match (first, second) {
+            (EcPoint::Infinity, _) => return second.clone(),
+            (_, EcPoint::Infinity) => return first.clone(),
+            (EcPoint::Affine { x: x1, y: y1 }, EcPoint::Affine { x: x2, y: y2 }) => {
+                (x1, y1, x2, y2)
+            }
+        };
+        if *second == self.negate(first) {
+            return EcPoint::Infinity;
+        }
+        let slope = if x1 == x2 {
+            let numerator = (BigUint::from(3u32) * x1 * x1 + &self.a) % &self.p;
+            numerator * mod_inverse(&(BigUint::from(2u32) * y1 % &self.p), &self.p).unwrap()
+        } else {
+            self.sub(y2, y1) * mod_inverse(&self.sub(x2, x1), &self.p).unwrap()
+        } % &self.p;
+        let x3 = self.sub(&(&slope * &slope), &(x1 + x2));
+        let y3 = self.sub(&(&slope * self.sub(x1, &x3)), y1);
+        EcPoint::new(x3, y3)
+    }
+
+    pub fn double(&self, point: &EcPoint) -> EcPoint {
+        self.add(point, point)
+    }
+
+    /// k * point by double and add, from the most significant bit down.
+    pub fn scalar_mul(&self, point: &EcPoint, k: &BigUint) -> EcPoint {
+        let mut result = EcPoint::Infinity;
+        for i in (0..k.bits()).rev() {
+            result = self.double(&result);
+            if k.bit(i) {
+                result = self.add(&result, point);
+            }
+        }
+        result
+    }
+}
+
+impl Group for WeierstrassCurve {
+    type Element = EcPoint;
+
+    fn identity(&self) -> EcPoint {
+        EcPoint::Infinity
+    }
+
+    fn combine(&self, a: &EcPoint, b: &EcPoint) -> EcPoint {
+        self.add(a, b)
+    }
+
+    fn inverse(&self, a: &EcPoint) -> EcPoint {
+        self.negate(a)
+    }
+
+    fn power(&self, base: &EcPoint, exp: &BigUint) -> EcPoint {
+        self.scalar_mul(base, exp)
+    }
+
+    fn fingerprint(&self, a: &EcPoint) -> u64 {
+        match a {
+            EcPoint::Infinity => 0,
+            EcPoint::Affine { x, .. } => x.iter_u64_digits().next().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EcPublicKey {
+    pub curve: WeierstrassCurve,
+    pub point: EcPoint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EcPrivateKey {
+    pub curve: WeierstrassCurve,
+    pub d: BigUint,
+    pub point: EcPoint,
+}
+
+impl EcPrivateKey {
+    pub fn public_key(&self) -> EcPublicKey {
+        EcPublicKey {
+            curve: self.curve.clone(),
+            point: self.point.clone(),
+        }
+    }
+}
+
+pub fn generate_ec_keys(curve: &WeierstrassCurve) -> (EcPublicKey, EcPrivateKey) {
+    let d = random_range(&BigUint::one(), &curve.n);
+    let point = curve.scalar_mul(&curve.g, &d);
+    let private = EcPrivateKey {
+        curve: curve.clone(),
+        d,
+        point,
+    };
+    (private.public_key(), private)
+}
+
+/// ECDH, d times the other party's point. Points that are not on the curve
+/// are rejected, and so is a shared secret at infinity.
+pub fn ecdh_shared_secret(key: &EcPrivateKey, other: &EcPoint) -> Result<EcPoint, &'static str> {
+    if *other == EcPoint::Infinity || !key.curve.is_on_curve(other) {
+        return Err("Public key is not a point on the curve");
+    }
+    match key.curve.scalar_mul(other, &key.d) {
+        EcPoint::Infinity => Err("Shared secret is the point at infinity"),
+        secret => Ok(secret),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EcdsaSignature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+/// SHA-256 of the message as an integer, cut down to the bit length of n.
+pub fn ecdsa_hash(message: &[u8], n: &BigUint) -> BigUint {
+    let digest = bytes_to_bigint(&sha256(message));
+    digest >> 256u64.saturating_sub(n.bits())
+}
+
+pub fn ecdsa_sign(message: &[u8], key: &EcPrivateKey) -> EcdsaSignature {
+    let curve = &key.curve;
+    let n = &curve.n;
+    loop {
+        let k = random_range(&BigUint::one(), n);
+        let r = match curve.scalar_mul(&curve.g, &k) {
+            EcPoint::Affine { x, .. } => x % n,
+            EcPoint::Infinity => continue,
+        };
+        let k_inverse = mod_inverse(&k, n).unwrap();
+        let s = k_inverse * (ecdsa_hash(message, n) + &key.d * &r) % n;
+        if !r.is_zero() && !s.is_zero() {
+            return EcdsaSignature { r, s };
+        }
+    }
+}
+
+/// ECDSA verification, r has to be the x coordinate of
+/// H(m)/s * G + r/s * Q mod n.
+pub fn ecdsa_verify(message: &[u8], signature: &EcdsaSignature, key: &EcPublicKey) -> bool {
+    let curve = &key.curve;
+    let n = &curve.n;
+    let EcdsaSignature { r, s } = signature;
+    if r.is_zero() || s.is_zero() || r >= n || s >= n {
+        return false;
+    }
+    let w = match mod_inverse(s, n) {
+        Ok(w) => w,
+        Err(_) => return false,
+    };
+    let u1 = ecdsa_hash(message, n) * &w % n;
+    let u2 = r * &w % n;
+    let point = curve.add(
+        &curve.scalar_mul(&curve.g, &u1),
+        &curve.scalar_mul(&key.point, &u2),
+    );
+    match point {
+        EcPoint::Affine { x, .. } => x % n == *r,
+        EcPoint::Infinity => false,
+    }
+}
+
+// CryptoPals Set 8 Challenge 61
+/// Duplicate signature key selection. ECDSA does not commit to the
+/// generator, so pick any d' and G' = R / (u1 + u2 d') with
+/// R = u1 G + u2 Q. Then u1 G' + u2 d' G' = R and the signature verifies
+/// under Q' = d' G' on the curve with generator G'.
+pub fn ecdsa_duplicate_signature_key(
+    message: &[u8],
+    signature: &EcdsaSignature,
+    key: &EcPublicKey,
+) -> Result<EcPrivateKey, &'static str> {
+    let curve = &key.curve;
+    let n = &curve.n;
+    let w = mod_inverse(&signature.s, n)?;
+    let u1 = ecdsa_hash(message, n) * &w % n;
+    let u2 = &signature.r * &w % n;
+    let point = curve.add(
+        &curve.scalar_mul(&curve.g, &u1),
+        &curve.scalar_mul(&key.point, &u2),
+    );
+    loop {
+        let d = random_range(&BigUint::one(), n);
+        let t = match mod_inverse(&((&u1 + &u2 * &d) % n), n) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        let g = curve.scalar_mul(&point, &t);
+        let curve = WeierstrassCurve {
+            g: g.clone(),
+            ..curve.clone()
+        };
+        return Ok(EcPrivateKey {
+            point: curve.scalar_mul(&g, &d),
+            curve,
+            d,
+        });
+    }
+}
+
+/// ECDH without any checks on the other party's point.
+pub fn ecdh_shared_secret_unchecked(key: &EcPrivateKey, other: &EcPoint) -> EcPoint {
+    key.curve.scalar_mul(other, &key.d)
+}
+
+fn ecdh_mac(secret: &EcPoint) -> Vec<u8> {
+    hmac_sha256(&secret.to_bytes(), ECDH_VICTIM_MESSAGE)
+}
+
+/// A victim that answers any public point with a message and its
+/// HMAC-SHA256 under the ECDH shared secret. Points not on the curve are
+/// rejected.
+pub fn ecdh_mac_victim(
+    key: &EcPrivateKey,
+) -> impl Fn(&EcPoint) -> Result<(Vec<u8>, Vec<u8>), &'static str> {
+    let key = key.clone();
+    move |point: &EcPoint| {
+        let secret = ecdh_shared_secret(&key, point)?;
+        Ok((ECDH_VICTIM_MESSAGE.to_vec(), ecdh_mac(&secret)))
+    }
+}
+
+/// Same as ecdh_mac_victim, but it trusts whatever point it gets.
+pub fn ecdh_mac_victim_unchecked(
+    key: &EcPrivateKey,
+) -> impl Fn(&EcPoint) -> Result<(Vec<u8>, Vec<u8>), &'static str> {
+    let key = key.clone();
+    move |point: &EcPoint| {
+        let secret = ecdh_shared_secret_unchecked(&key, point);
+        Ok((ECDH_VICTIM_MESSAGE.to_vec(), ecdh_mac(&secret)))
+    }
+}
+
+/// A random point of prime order r on a curve with the given order. The
+/// r-part of the group need not be cyclic, so the random point is first
+/// multiplied by the order without any factors of r and then by r until
+/// one more step would reach infinity.
+pub fn point_of_order(
+    curve: &WeierstrassCurve,
+    order: &BigUint,
+    r: u64,
+) -> Result<EcPoint, &'static str> {
+    if r < 2 || !(order % r).is_zero() {
+        return Err("Order is not a multiple of r");
+    }
+    let mut cofactor = order.clone();
+    while (&cofactor % r).is_zero() {
+        cofactor /= r;
+    }
+    let r = BigUint::from(r);
+    loop {
+        let mut point = curve.scalar_mul(&curve.random_point(), &cofactor);
+        if point == EcPoint::Infinity {
+            continue;
+        }
+        loop {
+            let next = curve.scalar_mul(&point, &r);
+            if next == EcPoint::Infinity {
+                return Ok(point);
+            }
+            point = next;
+        }
+    }
+}
+
+/// Invalid curve attack on ECDH. Points of small order r on curves with a
+/// different b confine the victim's shared secret to r values, brute
+/// forcing the MAC gives d mod r. Distinct prime factors below `bound`
+/// from the orders of INVALID_CURVES are used until their product exceeds
+/// the order of the base point, then combined with the CRT.
+pub fn invalid_curve_attack(
+    curve: &WeierstrassCurve,
+    victim: impl Fn(&EcPoint) -> Result<(Vec<u8>, Vec<u8>), &'static str>,
+    bound: u64,
+) -> Result<BigUint, &'static str> {
+    let mut residues = Vec::new();
+    let mut moduli: Vec<BigUint> = Vec::new();
+    let mut modulus = BigUint::one();
+    'curves: for (b, order) in INVALID_CURVES {
+        let invalid = WeierstrassCurve {
+            b: BigUint::from(b),
+            ..curve.clone()
+        };
+        let order = parse(order, 10);
+        let (factors, _) = trial_division(&order, bound);
+        for (r, _) in factors {
+            if moduli.contains(&BigUint::from(r)) {
+                continue;
+            }
+            let point = point_of_order(&invalid, &order, r)?;
+            let (message, mac) = victim(&point)?;
+            let mut secret = EcPoint::Infinity;
+            let residue = (0..r)
+                .find(|_| {
+                    let found = hmac_sha256(&secret.to_bytes(), &message) == mac;
+                    secret = curve.add(&secret, &point);
+                    found
+                })
+                .ok_or("No residue matches the MAC")?;
+            residues.push(BigUint::from(residue));
+            moduli.push(BigUint::from(r));
+            modulus *= r;
+            if modulus > curve.n {
+                break 'curves;
+            }
+        }
+    }
+    if modulus <= curve.n {
+        return Err("Small factors do not cover the order of the base point");
+    }
+    Ok(chinese_remainder(&residues, &moduli)?.0)
+}
+
+/// A Montgomery curve Bv^2 = u^3 + Au^2 + u over F_p with a base point of
+/// prime order n. `order` is the order of the whole curve, the twist has
+/// order 2p + 2 - order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MontgomeryCurve {
+    pub p: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    pub u: BigUint,
+    pub v: BigUint,
+    pub n: BigUint,
+    pub order: BigUint,
+}
+
+impl MontgomeryCurve {
+    /// v^2 = u^3 + 534u^2 + u, the Cryptopals curve in Montgomery form.
+    pub fn cryptopals() -> MontgomeryCurve {
+        let n = parse(CRYPTOPALS_N, 10);
+        MontgomeryCurve {
+            p: parse(CRYPTOPALS_P, 10),
+            a: BigUint::from(CRYPTOPALS_MONTGOMERY_A),
+            b: BigUint::one(),
+            u: BigUint::from(CRYPTOPALS_MONTGOMERY_U),
+            v: parse(CRYPTOPALS_GY, 10),
+            order: &n * 8u32,
+            n,
+        }
+    }
+
+    pub fn twist_order(&self) -> BigUint {
+        (&self.p + 1u32) * 2u32 - &self.order
+    }
+
+    fn inverse(&self, a: &BigUint) -> BigUint {
+        mod_inverse(a, &self.p).unwrap()
+    }
+
+    // (u^3 + Au^2 + u) / B, a square exactly when u is on the curve and
+    // not on the twist.
+    fn v_squared(&self, u: &BigUint) -> BigUint {
+        (u * u * u + &self.a * u * u + u) * self.inverse(&self.b) % &self.p
+    }
+
+    pub fn is_on_curve(&self, u: &BigUint) -> bool {
+        mod_sqrt(&self.v_squared(u), &self.p).is_some()
+    }
+
+    /// The same curve in short Weierstrass form, x = u/B + A/3B and y = v/B.
+    pub fn to_weierstrass(&self) -> WeierstrassCurve {
+        let p = &self.p;
+        let a_squared = &self.a * &self.a;
+        let three_b_squared = BigUint::from(3u32) * &self.b * &self.b % p;
+        let twenty_seven_b_cubed = BigUint::from(27u32) * &self.b * &self.b * &self.b % p;
+        let mut curve = WeierstrassCurve {
+            p: p.clone(),
+            a: (BigUint::from(3u32) + p - &a_squared % p) * self.inverse(&three_b_squared) % p,
+            b: (BigUint::from(2u32) * &a_squared * &self.a + p - BigUint::from(9u32) * &self.a % p)
+                * self.inverse(&twenty_seven_b_cubed)
+                % p,
+            g: EcPoint::Infinity,
+            n: self.n.clone(),
+        };
+        curve.g = self.to_weierstrass_point(&self.u, &self.v);
+        curve
+    }
+
+    pub fn to_weierstrass_point(&self, u: &BigUint, v: &BigUint) -> EcPoint {
+        let b_inverse = self.inverse(&self.b);
+        let shift = &self.a * self.inverse(&(BigUint::from(3u32) * &self.b));
+        EcPoint::new((u * &b_inverse + shift) % &self.p, v * &b_inverse % &self.p)
+    }
+
+    /// u = Bx - A/3 and v = By, None for the point at infinity.
+    pub fn to_montgomery_point(&self, point: &EcPoint) -> Option<(BigUint, BigUint)> {
+        match point {
+            EcPoint::Infinity => None,
+            EcPoint::Affine { x, y } => {
+                let shift = &self.a * self.inverse(&BigUint::from(3u32)) % &self.p;
+                Some((
+                    (&self.b * x + &self.p - shift) % &self.p,
+                    &self.b * y % &self.p,
+                ))
+            }
+        }
+    }
+
+    /// The u coordinate of k times the point with coordinate u, using only
+    /// u coordinates. Infinity comes out as 0, the same as the point of
+    /// order two. It works the same for u on the twist.
+    pub fn ladder(&self, u: &BigUint, k: &BigUint) -> BigUint {
+        let p = &self.p;
+        let sub = |a: &BigUint, b: &BigUint| (a % p + p - b % p) % p;
+        let (mut u2, mut w2) = (BigUint::one(), BigUint::zero());
+        let (mut u3, mut w3) = (u % p, BigUint::one());
+        for i in (0..k.bits()).rev() {
+            if k.bit(i) {
+                std::mem::swap(&mut u2, &mut u3);
+                std::mem::swap(&mut w2, &mut w3);
+            }
+            let sum = sub(&(&u2 * &u3), &(&w2 * &w3));
+            let difference = sub(&(&u2 * &w3), &(&w2 * &u3));
+            u3 = &sum * &sum % p;
+            w3 = u * &difference * &difference % p;
+            let square = sub(&(&u2 * &u2), &(&w2 * &w2));
+            let u2w2 = &u2 * &w2 % p;
+            let w2_next =
+                BigUint::from(4u32) * &u2w2 * ((&u2 * &u2 + &self.a * &u2w2 + &w2 * &w2) % p) % p;
+            u2 = &square * &square % p;
+            w2 = w2_next;
+            if k.bit(i) {
+                std::mem::swap(&mut u2, &mut u3);
+                std::mem::swap(&mut w2, &mut w3);
+            }
+        }
+        if w2.is_zero() {
+            return BigUint::zero();
+        }
+        u2 * self.inverse(&w2) % p
+    }
+
+    // A random u on the twist with order exactly the product of `primes`,
+    // which all divide the twist order once.
+    fn twist_point_of_order(&self, primes: &[u64]) -> BigUint {
+        let product: BigUint = primes.iter().map(|r| BigUint::from(*r)).product();
+        let cofactor = self.twist_order() / &product;
+        loop {
+            let u = random_range(&BigUint::one(), &self.p);
+            if self.is_on_curve(&u) {
+                continue;
+            }
+            let point = self.ladder(&u, &cofactor);
+            if primes
+                .iter()
+                .all(|r| !self.ladder(&point, &(&product / *r)).is_zero())
+            {
+                return point;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MontgomeryPrivateKey {
+    pub curve: MontgomeryCurve,
+    pub d: BigUint,
+    pub u: BigUint,
+}
+
+impl MontgomeryPrivateKey {
+    pub fn new(curve: &MontgomeryCurve, d: BigUint) -> MontgomeryPrivateKey {
+        MontgomeryPrivateKey {
+            u: curve.ladder(&curve.u, &d),
+            curve: curve.clone(),
+            d,
+        }
+    }
+}
+
+/// A private key, the public key is just its u coordinate.
+pub fn generate_montgomery_key(curve: &MontgomeryCurve) -> MontgomeryPrivateKey {
+    MontgomeryPrivateKey::new(curve, random_range(&BigUint::one(), &curve.n))
+}
+
+/// A victim with an X25519 style API, it takes any u coordinate and
+/// answers with a message and its HMAC-SHA256 under the u coordinate of the
+/// shared secret. There is no v to check so twist points go through.
+pub fn montgomery_mac_victim(
+    key: &MontgomeryPrivateKey,
+) -> impl Fn(&BigUint) -> (Vec<u8>, Vec<u8>) {
+    let key = key.clone();
+    move |u: &BigUint| {
+        let secret = key.curve.ladder(u, &key.d);
+        let mac = hmac_sha256(&bigint_to_bytes(&secret), ECDH_VICTIM_MESSAGE);
+        (ECDH_VICTIM_MESSAGE.to_vec(), mac)
+    }
+}
+
+/// Twist attack on x-only ECDH. Points of small order r on the twist give
+/// away d mod r up to sign, the u coordinates of kP and -kP are the same.
+/// A second query with a point of order r0 r fixes the sign relative to the
+/// first factor with a non-zero residue, which leaves d = ±c mod R for the
+/// product R of the primes below `bound` that divide the twist order once.
+/// The rest of d, up to `high`, is a discrete log on the curve itself for
+/// `solver`, tried for both signs of c and both square roots v of the
+/// public key.
+pub fn twist_attack(
+    curve: &MontgomeryCurve,
+    public: &BigUint,
+    victim: impl Fn(&BigUint) -> (Vec<u8>, Vec<u8>),
+    bound: u64,
+    high: &BigUint,
+    solver: &impl DiscreteLogSolver,
+) -> Result<BigUint, &'static str> {
+    let matches = |u: &BigUint, k: &BigUint, message: &[u8], mac: &[u8]| {
+        hmac_sha256(&bigint_to_bytes(&curve.ladder(u, k)), message) == mac
+    };
+
+    let (factors, _) = trial_division(&curve.twist_order(), bound);
+    let primes: Vec<u64> = factors
+        .iter()
+        .filter(|(r, e)| *r > 2 && *e == 1)
+        .map(|(r, _)| *r)
+        .collect();
+    if primes.is_empty() {
+        return Err("No small factors of the twist order below the bound");
+    }
+    let mut residues: Vec<BigUint> = Vec::new();
+    let mut moduli: Vec<BigUint> = Vec::new();
+    // A zero residue is its own negation and says nothing about the sign.
+    let mut anchor: Option<(BigUint, u64)> = None;
+    for r in primes {
+        let u = curve.twist_point_of_order(&[r]);
+        let (message, mac) = victim(&u);
+        let mut residue = (0..=r / 2)
+            .map(BigUint::from)
+            .find(|k| matches(&u, k, &message, &mac))
+            .ok_or("No residue matches the MAC")?;
+        let modulus = BigUint::from(r);
+        if let Some((first, first_prime)) = &anchor {
+            if !residue.is_zero() {
+                let u = curve.twist_point_of_order(&[*first_prime, r]);
+                let (message, mac) = victim(&u);
+                let moduli = [BigUint::from(*first_prime), modulus.clone()];
+                let (k, _) = chinese_remainder(&[first.clone(), residue.clone()], &moduli)?;
+                if !matches(&u, &k, &message, &mac) {
+                    residue = &modulus - residue;
+                }
+            }
+        } else if !residue.is_zero() {
+            anchor = Some((residue.clone(), r));
+        }
+        residues.push(residue);
+        moduli.push(modulus);
+    }
+    let (c, modulus) = chinese_remainder(&residues, &moduli)?;
+
+    let weierstrass = curve.to_weierstrass();
+    let v = mod_sqrt(&curve.v_squared(public), &curve.p).ok_or("Public key is on the twist")?;
+    let target = curve.to_weierstrass_point(public, &v);
+    let base = weierstrass.power(&weierstrass.g, &modulus);
+    for target in [target.clone(), weierstrass.negate(&target)] {
+        for residue in [c.clone(), (&modulus - &c) % &modulus] {
+            if &residue > high {
+                continue;
+            }
+            let shifted = weierstrass.add(
+                &target,
+                &weierstrass.negate(&weierstrass.power(&weierstrass.g, &residue)),
+            );
+            let m_high = (high - &residue) / &modulus;
+            if let Some(m) = solver.solve(&weierstrass, &base, &shifted, &BigUint::zero(), &m_high)
+            {
+                let d = residue + m * &modulus;
+                if curve.ladder(&curve.u, &d) == *public {
+                    return Ok(d);
+                }
+            }
+        }
+    }
+    Err("Discrete log not found in the remaining range")
+}
@@ -0,0 +1,34 @@
+use openssl::sha::sha256;
+
+pub const SHA256_BLOCK_SIZE: usize = 64usize;
+
+/// HMAC (RFC 2104) on top of any hash function with the given block size.
+/// Keys longer than a block are hashed first, shorter ones zero padded.
+pub fn hmac(
+    hash: impl Fn(&[u8]) -> Vec<u8>,
+    block_size: usize,
+    key: &[u8],
+    message: &[u8],
+) -> Vec<u8> {
+    let mut key = if key.len() > block_size {
+        hash(key)
+    } else {
+        key.to_vec()
+    };
+    key.resize(block_size, 0);
+
+    let mut inner: Vec<u8> = key.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = key.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend(hash(&inner));
+    hash(&outer)
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac(
+        |data| sha256(data).to_vec(),
+        SHA256_BLOCK_SIZE,
+        key,
+        message,
+    )
+}
@@ -0,0 +1,284 @@
+use crate::bigint::{mod_inverse, modulo, BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+// Polynomials are Vecs of coefficients with the constant term first.
+
+fn poly_trim(mut poly: Vec<BigInt>) -> Vec<BigInt> {
+    while poly.len() > 1 && poly.last().unwrap().is_zero() {
+        poly.pop();
+    }
+    poly
+}
+
+fn poly_mul(a: &[BigInt], b: &[BigInt]) -> Vec<BigInt> {
+    let mut product = vec![BigInt::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    product
+}
+
+/// Raise a polynomial to a non negative power.
+pub fn poly_pow(poly: &[BigInt], exp: usize) -> Vec<BigInt> {
+    let mut result = vec![BigInt::one()];
+    for _ in 0..exp {
+        result = poly_mul(&result, poly);
+    }
+    result
+}
+
+/// Evaluate a polynomial at x using Horner's method.
+pub fn poly_eval(poly: &[BigInt], x: &BigInt) -> BigInt {
+    poly.iter()
+        .rev()
+        .fold(BigInt::zero(), |acc, coefficient| acc * x + coefficient)
+}
+
+fn poly_derivative(poly: &[BigInt]) -> Vec<BigInt> {
+    if poly.len() <= 1 {
+        return vec![BigInt::zero()];
+    }
+    poly.iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, coefficient)| coefficient * BigInt::from(i))
+        .collect()
+}
+
+// Round num / den to the nearest integer, den has to be positive.
+fn round_div(num: &BigInt, den: &BigInt) -> BigInt {
+    let doubled: BigInt = num << 1u32;
+    (doubled + den).div_floor(&(den << 1u32))
+}
+
+/// Reduce a lattice basis with the LLL algorithm (delta = 3/4). Uses the
+/// integral version from Cohen's "A Course in Computational Algebraic Number
+/// Theory" (algorithm 2.6.7) so no rationals are needed. The rows of the
+/// basis have to be linearly independent.
+pub fn lll_reduce(basis: &[Vec<BigInt>]) -> Vec<Vec<BigInt>> {
+    let mut b = basis.to_vec();
+    let n = b.len();
+    if n < 2 {
+        return b;
+    }
+    let dot =
+        |x: &[BigInt], y: &[BigInt]| -> BigInt { x.iter().zip(y.iter()).map(|(a, c)| a * c).sum() };
+
+    // d[i + 1] is the d_i of the algorithm so d[0] can hold d_0 = 1
+    let mut d = vec![BigInt::zero(); n + 1];
+    let mut lambda = vec![vec![BigInt::zero(); n]; n];
+    d[0] = BigInt::one();
+    d[1] = dot(&b[0], &b[0]);
+    let mut k = 1usize;
+    let mut k_max = 0usize;
+
+    let reduce = |b: &mut Vec<Vec<BigInt>>,
+                  lambda: &mut Vec<Vec<BigInt>>,
+                  d: &[BigInt],
+                  k: usize,
+                  l: usize| {
+        let doubled: BigInt = &lambda[k][l] << 1u32;
+        if doubled.abs() > d[l + 1] {
+            let q = round_div(&lambda[k][l], &d[l + 1]);
+            let row = b[l].clone();
+            for (x, y) in b[k].iter_mut().zip(row.iter()) {
+                *x -= &q * y;
+            }
+            lambda[k][l] -= &q * &d[l + 1];
+            let row = lambda[l].clone();
+            for (x, y) in lambda[k].iter_mut().zip(row.iter()).take(l) {
+                *x -= &q * y;
+            }
+        }
+    };
+
+    while k < n {
+        if k > k_max {
+            k_max = k;
+            for j in 0..=k {
+                let mut u = dot(&b[k], &b[j]);
+                for i in 0..j {
+                    u = (&d[i + 1] * &u - &lambda[k][i] * &lambda[j][i]) / &d[i];
+                }
+                if j < k {
+                    lambda[k][j] = u;
+                } else {
+                    d[k + 1] = u;
+                }
+            }
+        }
+
+        loop {
+            reduce(&mut b, &mut lambda, &d, k, k - 1);
+            let lhs = &d[k + 1] * &d[k - 1] * 4u32;
+            let rhs = &d[k] * &d[k] * 3u32 - &lambda[k][k - 1] * &lambda[k][k - 1] * 4u32;
+            if lhs >= rhs {
+                break;
+            }
+            b.swap(k, k - 1);
+            let (upper, lower) = lambda.split_at_mut(k);
+            upper[k - 1][..k - 1].swap_with_slice(&mut lower[0][..k - 1]);
+            let l = lambda[k][k - 1].clone();
+            let big_b = (&d[k - 1] * &d[k + 1] + &l * &l) / &d[k];
+            for row in lambda.iter_mut().take(k_max + 1).skip(k + 1) {
+                let t = row[k].clone();
+                row[k] = (&d[k + 1] * &row[k - 1] - &l * &t) / &d[k];
+                row[k - 1] = (&big_b * &t + &l * &row[k]) / &d[k + 1];
+            }
+            d[k] = big_b;
+            if k > 1 {
+                k -= 1;
+            }
+        }
+
+        for l in (0..k.saturating_sub(1)).rev() {
+            reduce(&mut b, &mut lambda, &d, k, l);
+        }
+        k += 1;
+    }
+    b
+}
+
+// Floors of the real roots of poly inside [low, high]. The real roots of
+// the derivative split the range into pieces where poly is monotone, each
+// of which can then be bisected.
+fn real_root_floors(poly: &[BigInt], low: &BigInt, high: &BigInt) -> Vec<BigInt> {
+    let poly = poly_trim(poly.to_vec());
+    if poly.len() <= 1 {
+        return Vec::new();
+    }
+
+    let mut points: Vec<BigInt> = vec![low.clone(), high.clone()];
+    if poly.len() > 2 {
+        for critical in real_root_floors(&poly_derivative(&poly), low, high) {
+            points.push(critical.clone());
+            points.push(critical + 1);
+        }
+    }
+    points.retain(|x| x >= low && x <= high);
+    points.sort();
+    points.dedup();
+
+    let mut floors: Vec<BigInt> = Vec::new();
+    for pair in points.windows(2) {
+        let (start, end) = (&pair[0], &pair[1]);
+        let start_value = poly_eval(&poly, start);
+        if start_value.is_zero() {
+            floors.push(start.clone());
+            continue;
+        }
+        let end_value = poly_eval(&poly, end);
+        if end_value.is_zero() || start_value.sign() == end_value.sign() {
+            continue;
+        }
+        // Bisect down to the two integers around the root
+        let (mut lo, mut hi) = (start.clone(), end.clone());
+        while &hi - &lo > BigInt::one() {
+            let mid: BigInt = (&lo + &hi) >> 1;
+            if poly_eval(&poly, &mid).sign() == start_value.sign() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        if poly_eval(&poly, &hi).is_zero() {
+            floors.push(hi);
+        } else {
+            floors.push(lo);
+        }
+    }
+    if poly_eval(&poly, high).is_zero() {
+        floors.push(high.clone());
+    }
+    floors.sort();
+    floors.dedup();
+    floors
+}
+
+/// All integer roots of a polynomial inside [low, high].
+pub fn integer_roots(poly: &[BigInt], low: &BigInt, high: &BigInt) -> Vec<BigInt> {
+    real_root_floors(poly, low, high)
+        .into_iter()
+        .filter(|x| poly_eval(poly, x).is_zero())
+        .collect()
+}
+
+/// Find the roots x0 < bound of poly(x) = 0 mod modulus using Coppersmith's
+/// method in Howgrave-Graham's formulation. The lattice is spanned by
+/// N^(m - i) x^j f^i for i < m, j < deg(f) and f^m which finds every root
+/// with roughly bound < N^((dm - d + 2) / (d(dm + 1))), approaching N^(1/d)
+/// as the depth m grows.
+pub fn coppersmith_small_roots(
+    poly: &[BigInt],
+    modulus: &BigUint,
+    bound: &BigUint,
+    depth: usize,
+) -> Result<Vec<BigUint>, &'static str> {
+    let poly = poly_trim(
+        poly.iter()
+            .map(|c| BigInt::from(modulo(c, modulus)))
+            .collect(),
+    );
+    let degree = poly.len() - 1;
+    if degree == 0 {
+        return Err("Polynomial has to have a positive degree");
+    }
+    if depth == 0 {
+        return Err("Lattice depth has to be positive");
+    }
+
+    // Make the polynomial monic
+    let leading_inverse = mod_inverse(&modulo(&poly[degree], modulus), modulus)?;
+    let leading_inverse = BigInt::from(leading_inverse);
+    let n = BigInt::from(modulus.clone());
+    let monic: Vec<BigInt> = poly
+        .iter()
+        .map(|c| BigInt::from(modulo(&(c * &leading_inverse), modulus)))
+        .collect();
+
+    let x = BigInt::from(bound.clone());
+    let dimension = degree * depth + 1;
+    let mut rows: Vec<Vec<BigInt>> = Vec::new();
+    for i in 0..=depth {
+        let shifts = if i == depth { 1 } else { degree };
+        let f_pow = poly_pow(&monic, i);
+        let n_pow = num_traits::pow(n.clone(), depth - i);
+        for j in 0..shifts {
+            let mut row = vec![BigInt::zero(); dimension];
+            for (idx, coefficient) in f_pow.iter().enumerate() {
+                // Substitute x -> bound * x so the lattice measures h(bound * x)
+                row[idx + j] = coefficient * &n_pow * num_traits::pow(x.clone(), idx + j);
+            }
+            rows.push(row);
+        }
+    }
+
+    let reduced = lll_reduce(&rows);
+    let low = BigInt::zero();
+    let high = &x - 1;
+    let mut roots: Vec<BigUint> = Vec::new();
+    for row in reduced.iter() {
+        let mut candidate_poly: Vec<BigInt> = Vec::new();
+        for (idx, coefficient) in row.iter().enumerate() {
+            let x_pow = num_traits::pow(x.clone(), idx);
+            if !coefficient.is_multiple_of(&x_pow) {
+                return Err("Reduced lattice vector is not a scaled polynomial");
+            }
+            candidate_poly.push(coefficient / x_pow);
+        }
+        for root in integer_roots(&candidate_poly, &low, &high) {
+            if poly_eval(&monic, &root).is_multiple_of(&n) {
+                roots.push(root.to_biguint().unwrap());
+            }
+        }
+        if !roots.is_empty() {
+            break;
+        }
+    }
+    roots.sort();
+    roots.dedup();
+    Ok(roots)
+}
@@ -2,20 +2,97 @@ extern crate hex;
 
 mod aes;
 mod b64;
+mod bigint;
+mod cbc_mac;
+mod compression;
+mod dh;
+mod dlog;
+mod dsa;
+mod ec;
+mod hmac;
+mod lattice;
+mod md4;
+mod pkcs1;
+mod rc4;
+mod rsa;
+mod sha1;
 mod utils;
+mod weak_hash;
 mod xor;
 
 #[cfg(test)]
 mod tests {
     use crate::aes::{
-        decrypt_aes_cbc, decrypt_aes_ecb, detect_aes_ecb, encrypt_aes_cbc, encrypt_aes_ecb,
+        aes_ctr, decrypt_aes_cbc, decrypt_aes_ecb, detect_aes_ecb, encrypt_aes_cbc, encrypt_aes_ecb,
     };
     use crate::b64::b64_decode;
+    use crate::bigint::{
+        bigint_to_bytes, bigint_to_bytes_padded, bytes_to_bigint, chinese_remainder, divmod,
+        exact_kth_root, gcd, generate_prime, generate_smooth_prime, is_probable_prime, kth_root,
+        mod_exp, mod_inverse, mod_sqrt, primes_below, random_bits, random_range, trial_division,
+        BigInt, BigUint,
+    };
+    use crate::cbc_mac::{
+        cbc_mac, cbc_mac_hash, forge_cbc_mac_hash_collision, forge_transfer_with_iv,
+        forge_transfers_with_length_extension, Transfer, TransferClient, TransferServer,
+    };
+    use crate::compression::{
+        compression_oracle, compression_ratio_attack, deflate, format_request, CompressionCipher,
+        CRIME_SESSION_ID,
+    };
+    use crate::dh::{
+        dh_mac_victim, dh_shared_secret, element_of_order, generate_dh_keys,
+        recover_dh_private_key, subgroup_confinement_attack, DhParameters,
+    };
+    use crate::dlog::{
+        pohlig_hellman, BabyStepGiantStep, DiscreteLogSolver, Group, Kangaroo, MultiplicativeGroup,
+    };
+    use crate::dsa::{
+        dsa_hash, dsa_magic_signature, dsa_sign, dsa_verify, dsa_verify_strict,
+        dsa_verify_unchecked, dsa_zero_generator_signature, generate_dsa_keys,
+        load_dsa_signed_messages, recover_dsa_key_from_nonce_range,
+        recover_dsa_keys_from_repeated_nonces, DsaParameters, DsaPublicKey, DsaSignature,
+    };
+    use crate::ec::{
+        ecdh_mac_victim, ecdh_mac_victim_unchecked, ecdh_shared_secret,
+        ecdsa_duplicate_signature_key, ecdsa_sign, ecdsa_verify, generate_ec_keys,
+        generate_montgomery_key, invalid_curve_attack, montgomery_mac_victim, point_of_order,
+        twist_attack, EcPoint, EcdsaSignature, MontgomeryCurve, MontgomeryPrivateKey,
+        WeierstrassCurve, INVALID_CURVES,
+    };
+    use crate::hmac::hmac_sha256;
+    use crate::lattice::{coppersmith_small_roots, integer_roots, lll_reduce};
+    use crate::md4::{
+        md4, md4_block, md4_trace, md4_words, wang_differential, wang_md4_collision,
+        wang_round_conditions_hold, MD4_INITIAL_STATE,
+    };
+    use crate::pkcs1::{
+        bleichenbacher_attack, forge_pkcs1_v15_signature, manger_attack, manger_oracle, mgf1_sha1,
+        oaep_decode, oaep_encode, pkcs1_v15_decrypt, pkcs1_v15_duplicate_signature_key,
+        pkcs1_v15_encrypt, pkcs1_v15_encryption_pad, pkcs1_v15_encryption_unpad,
+        pkcs1_v15_padding_oracle, pkcs1_v15_sign, pkcs1_v15_verify, pkcs1_v15_verify_permissive,
+        rsa_oaep_decrypt, rsa_oaep_encrypt, BleichenbacherVariant, HashAlgorithm,
+    };
+    use crate::rc4::{rc4, rc4_bias_attack, rc4_cookie_oracle, Rc4, RC4_COOKIE};
+    use crate::rsa::{
+        generate_rsa_keys, hastad_broadcast_attack, hastad_padded_broadcast_attack, rsa_decrypt,
+        rsa_decrypt_bigint, rsa_decrypt_bigint_crt, rsa_decryption_oracle, rsa_encrypt,
+        rsa_encrypt_bigint, rsa_encrypt_linear_padded, rsa_parity_attack, rsa_parity_oracle,
+        rsa_private_key_from_b64, rsa_private_key_to_b64, rsa_public_key_from_b64,
+        rsa_public_key_to_b64, unpadded_message_recovery, LinearPadding, PaddedBroadcast,
+        RsaPublicKey, RSA_DEFAULT_EXPONENT,
+    };
+    use crate::sha1::sha1;
     use crate::utils::{check_pkcs_7_padding, pkcs_7_pad};
+    use crate::weak_hash::{
+        cascade_hash, find_cascade_collision, joux_multicollision, kelsey_schneier_second_preimage,
+        weak_hash_padding, DiamondStructure, ExpandableMessage, WeakHash,
+    };
     use crate::xor::breaking_repeating_xor;
     use crate::{b64, utils, xor};
     use openssl::symm::{Cipher, Crypter, Mode};
     use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     // CryptoPals Set 1 Challenge 1
     #[test]
@@ -216,4 +293,1177 @@ mod tests {
         let result = check_pkcs_7_padding(&input);
         assert_eq!(result, Err("Data not PKCS#7 padded".to_string()));
     }
+
+    #[test]
+    fn test_bigint_bytes_conversion() {
+        let bytes = hex::decode("00ff0102").unwrap();
+        let n = bytes_to_bigint(&bytes);
+        assert_eq!(n, BigUint::from(0xff0102u32));
+        assert_eq!(bigint_to_bytes(&n), hex::decode("ff0102").unwrap());
+        assert_eq!(bigint_to_bytes_padded(&n, 4).unwrap(), bytes);
+        assert!(bigint_to_bytes_padded(&n, 2).is_err());
+    }
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        let a = BigUint::from(1071u32);
+        let b = BigUint::from(462u32);
+        assert_eq!(gcd(&a, &b), BigUint::from(21u32));
+        assert_eq!(
+            divmod(&a, &b).unwrap(),
+            (BigUint::from(2u32), BigUint::from(147u32))
+        );
+        assert!(divmod(&a, &BigUint::from(0u32)).is_err());
+        let m = BigUint::from(3120u32);
+        let inverse = mod_inverse(&BigUint::from(17u32), &m).unwrap();
+        assert_eq!(inverse, BigUint::from(2753u32));
+        assert!(mod_inverse(&BigUint::from(2u32), &BigUint::from(4u32)).is_err());
+        let result = mod_exp(
+            &BigUint::from(4u32),
+            &BigUint::from(13u32),
+            &BigUint::from(497u32),
+        );
+        assert_eq!(result, BigUint::from(445u32));
+        assert_eq!(random_bits(100).bits(), 100);
+        assert_eq!(random_bits(0), BigUint::from(0u32));
+        assert_eq!(generate_prime(64).unwrap().bits(), 64);
+        assert!(generate_prime(1).is_err());
+    }
+
+    #[test]
+    fn test_bigint_kth_root() {
+        let root = BigUint::parse_bytes(b"123456789123456789123456789", 10).unwrap();
+        let cube = root.pow(3);
+        assert_eq!(kth_root(&cube, 3), root);
+        assert_eq!(exact_kth_root(&cube, 3), Some(root.clone()));
+        assert_eq!(kth_root(&(&cube + 1u32), 3), root);
+        assert_eq!(exact_kth_root(&(&cube + 1u32), 3), None);
+    }
+
+    #[test]
+    fn test_miller_rabin() {
+        let primes = [2u32, 3, 97, 7919, 104729, 2147483647];
+        for prime in primes.iter() {
+            assert!(is_probable_prime(&BigUint::from(*prime), 20));
+        }
+        // 561 and 41041 are Carmichael numbers which fool the Fermat test
+        let composites = [0u32, 1, 4, 561, 41041, 2147483649];
+        for composite in composites.iter() {
+            assert!(!is_probable_prime(&BigUint::from(*composite), 20));
+        }
+    }
+
+    // CryptoPals Set 5 Challenge 39
+    #[test]
+    fn test_rsa_encrypt_decrypt() {
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        assert_eq!(public.n.bits(), 1024);
+        let m = BigUint::from(42u32);
+        let c = rsa_encrypt_bigint(&m, &public);
+        assert_eq!(rsa_decrypt_bigint(&c, &private), m);
+        assert_eq!(rsa_decrypt_bigint_crt(&c, &private), m);
+
+        let (public, private) = generate_rsa_keys(512, &BigUint::from(3u32));
+        let plaintext = "Hit it with the textbook".as_bytes().to_vec();
+        let ciphertext = rsa_encrypt(&plaintext, &public).unwrap();
+        assert_eq!(ciphertext.len(), 64);
+        assert_eq!(rsa_decrypt(&ciphertext, &private).unwrap(), plaintext);
+        assert!(rsa_encrypt(&[0xffu8; 65], &public).is_err());
+    }
+
+    #[test]
+    fn test_rsa_key_serialisation() {
+        let (public, private) = generate_rsa_keys(512, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let encoded = rsa_public_key_to_b64(&public);
+        assert_eq!(rsa_public_key_from_b64(&encoded).unwrap(), public);
+        let encoded = rsa_private_key_to_b64(&private);
+        assert_eq!(rsa_private_key_from_b64(&encoded).unwrap(), private);
+        assert!(rsa_public_key_from_b64("not base64!").is_err());
+        assert!(rsa_private_key_from_b64(&rsa_public_key_to_b64(&public)).is_err());
+    }
+
+    #[test]
+    fn test_chinese_remainder() {
+        let residues = [2u32, 3, 2].map(BigUint::from);
+        let moduli = [3u32, 5, 7].map(BigUint::from);
+        let (x, product) = chinese_remainder(&residues, &moduli).unwrap();
+        assert_eq!(x, BigUint::from(23u32));
+        assert_eq!(product, BigUint::from(105u32));
+        let moduli = [4u32, 6, 7].map(BigUint::from);
+        assert!(chinese_remainder(&residues, &moduli).is_err());
+    }
+
+    #[test]
+    fn test_lll_reduce() {
+        let basis: Vec<Vec<BigInt>> = [[1, 1, 1], [-1, 0, 2], [3, 5, 6]]
+            .iter()
+            .map(|row| row.iter().map(|x| BigInt::from(*x)).collect())
+            .collect();
+        let reduced = lll_reduce(&basis);
+        let answer: Vec<Vec<BigInt>> = [[0, 1, 0], [1, 0, 1], [-1, 0, 2]]
+            .iter()
+            .map(|row| row.iter().map(|x| BigInt::from(*x)).collect())
+            .collect();
+        assert_eq!(reduced, answer);
+    }
+
+    #[test]
+    fn test_integer_roots() {
+        // (x - 3)(x - 1000)(2x + 1) = 2x^3 - 2005x^2 + 4997x + 3000
+        let poly: Vec<BigInt> = [3000, 4997, -2005, 2]
+            .iter()
+            .map(|x| BigInt::from(*x))
+            .collect();
+        let roots = integer_roots(&poly, &BigInt::from(-100), &BigInt::from(5000));
+        assert_eq!(roots, vec![BigInt::from(3), BigInt::from(1000)]);
+    }
+
+    #[test]
+    fn test_coppersmith_small_roots() {
+        let (public, _) = generate_rsa_keys(512, &BigUint::from(3u32));
+        let root = random_bits(60);
+        // f(x) = x^3 + 5x + c with f(root) = 0 mod n
+        let c = &public.n - ((root.pow(3) + &root * 5u32) % &public.n);
+        let poly = vec![
+            BigInt::from(c),
+            BigInt::from(5),
+            BigInt::from(0),
+            BigInt::from(1),
+        ];
+        let roots = coppersmith_small_roots(&poly, &public.n, &(BigUint::from(1u32) << 64), 1);
+        assert_eq!(roots.unwrap(), vec![root]);
+    }
+
+    // CryptoPals Set 5 Challenge 40
+    #[test]
+    fn test_hastad_broadcast_attack() {
+        let e = BigUint::from(3u32);
+        let message = bytes_to_bigint("Hastad sends his regards".as_bytes());
+        let ciphertexts: Vec<(BigUint, RsaPublicKey)> = (0..3)
+            .map(|_| {
+                let (public, _) = generate_rsa_keys(512, &e);
+                (rsa_encrypt_bigint(&message, &public), public)
+            })
+            .collect();
+        assert_eq!(hastad_broadcast_attack(&ciphertexts).unwrap(), message);
+        assert!(hastad_broadcast_attack(&ciphertexts[..2]).is_err());
+    }
+
+    #[test]
+    fn test_hastad_padded_broadcast_attack() {
+        let e = BigUint::from(3u32);
+        let message = bytes_to_bigint("Linear padding is not enough".as_bytes());
+        let broadcast = |message: &BigUint, recipients: usize| -> Vec<PaddedBroadcast> {
+            (0..recipients)
+                .map(|_| {
+                    let (key, _) = generate_rsa_keys(512, &e);
+                    let padding = LinearPadding {
+                        a: random_bits(500),
+                        b: random_bits(500),
+                    };
+                    PaddedBroadcast {
+                        ciphertext: rsa_encrypt_linear_padded(message, &padding, &key),
+                        key,
+                        padding,
+                    }
+                })
+                .collect()
+        };
+        let broadcasts = broadcast(&message, 3);
+        let result = hastad_padded_broadcast_attack(&broadcasts, 224).unwrap();
+        assert_eq!(result, message);
+        // More recipients than e allow recovering messages almost as large as a modulus
+        let message = random_bits(400);
+        let broadcasts = broadcast(&message, 5);
+        let result = hastad_padded_broadcast_attack(&broadcasts, 400).unwrap();
+        assert_eq!(result, message);
+    }
+
+    // CryptoPals Set 6 Challenge 41
+    #[test]
+    fn test_unpadded_message_recovery() {
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let message = bytes_to_bigint("{time: 1356304276, social: '555-55-5555'}".as_bytes());
+        let ciphertext = rsa_encrypt_bigint(&message, &public);
+        let mut oracle = rsa_decryption_oracle(private);
+        assert_eq!(oracle.query(&ciphertext).unwrap(), Ok(message.clone()));
+        assert!(oracle.query(&ciphertext).is_err());
+        let result = unpadded_message_recovery(&ciphertext, &public, &mut oracle).unwrap();
+        assert_eq!(result, message);
+        assert_eq!(oracle.queries(), 2);
+    }
+
+    #[test]
+    fn test_sha1() {
+        let digest = sha1("The quick brown fox jumps over the lazy dog".as_bytes());
+        assert_eq!(
+            hex::encode(digest),
+            "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12"
+        );
+        assert_eq!(
+            hex::encode(sha1(&[])),
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+        );
+        let digest = sha1(&[0x61u8; 1000]);
+        assert_eq!(
+            hex::encode(digest),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+    }
+
+    #[test]
+    fn test_pkcs1_v15_signatures() {
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let message = "Sign me up".as_bytes();
+        for hash in [HashAlgorithm::Sha1, HashAlgorithm::Sha256] {
+            let signature = pkcs1_v15_sign(message, &private, hash).unwrap();
+            assert!(pkcs1_v15_verify(message, &signature, &public, hash));
+            assert!(pkcs1_v15_verify_permissive(
+                message, &signature, &public, hash
+            ));
+            assert!(!pkcs1_v15_verify(
+                "Sign me out".as_bytes(),
+                &signature,
+                &public,
+                hash
+            ));
+            assert!(!pkcs1_v15_verify_permissive(
+                "Sign me out".as_bytes(),
+                &signature,
+                &public,
+                hash
+            ));
+        }
+    }
+
+    // CryptoPals Set 6 Challenge 42
+    #[test]
+    fn test_forge_pkcs1_v15_signature() {
+        let message = "hi mom".as_bytes();
+        let (public, _) = generate_rsa_keys(1024, &BigUint::from(3u32));
+        let forged = forge_pkcs1_v15_signature(message, &public, HashAlgorithm::Sha1).unwrap();
+        assert!(pkcs1_v15_verify_permissive(
+            message,
+            &forged,
+            &public,
+            HashAlgorithm::Sha1
+        ));
+        assert!(!pkcs1_v15_verify(
+            message,
+            &forged,
+            &public,
+            HashAlgorithm::Sha1
+        ));
+        // SHA-256 has a longer DigestInfo and needs a larger modulus
+        assert!(forge_pkcs1_v15_signature(message, &public, HashAlgorithm::Sha256).is_err());
+        let (public, _) = generate_rsa_keys(2048, &BigUint::from(3u32));
+        let forged = forge_pkcs1_v15_signature(message, &public, HashAlgorithm::Sha256).unwrap();
+        assert!(pkcs1_v15_verify_permissive(
+            message,
+            &forged,
+            &public,
+            HashAlgorithm::Sha256
+        ));
+        assert!(!pkcs1_v15_verify(
+            message,
+            &forged,
+            &public,
+            HashAlgorithm::Sha256
+        ));
+    }
+
+    #[test]
+    fn test_dsa_sign_verify() {
+        let params = DsaParameters::cryptopals();
+        let (public, private) = generate_dsa_keys(&params);
+        let message = "Digital signatures for all".as_bytes();
+        let signature = dsa_sign(message, &private);
+        assert!(dsa_verify(message, &signature, &public));
+        assert!(!dsa_verify(
+            "Digital signatures for none".as_bytes(),
+            &signature,
+            &public
+        ));
+        let (other, _) = generate_dsa_keys(&params);
+        assert!(!dsa_verify(message, &signature, &other));
+    }
+
+    // CryptoPals Set 6 Challenge 43
+    #[test]
+    fn test_dsa_key_recovery_from_nonce() {
+        let message = "For those that envy a MC it can be hazardous to your health\nSo be friendly, a matter of life and death, just like a etch-a-sketch\n".as_bytes();
+        let hash = dsa_hash(message);
+        assert_eq!(
+            hash,
+            BigUint::parse_bytes(b"d2d0714f014a9784047eaeccf956520045c45265", 16).unwrap()
+        );
+        let public = DsaPublicKey {
+            params: DsaParameters::cryptopals(),
+            y: BigUint::parse_bytes(b"84ad4719d044495496a3201c8ff484feb45b962e7302e56a392aee4abab3e4bdebf2955b4736012f21a08084056b19bcd7fee56048e004e44984e2f411788efdc837a0d2e5abb7b555039fd243ac01f0fb2ed1dec568280ce678e931868d23eb095fde9d3779191b8c0299d6e07bbb283e6633451e535c45513b2d33c99ea17", 16).unwrap(),
+        };
+        let signature = DsaSignature {
+            r: BigUint::parse_bytes(b"548099063082341131477253921760299949438196259240", 10)
+                .unwrap(),
+            s: BigUint::parse_bytes(b"857042759984254168557880549501802188789837994940", 10)
+                .unwrap(),
+        };
+        let x = recover_dsa_key_from_nonce_range(&hash, &signature, &public, 0..1 << 16).unwrap();
+        let fingerprint = sha1(format!("{:x}", x).as_bytes());
+        assert_eq!(
+            hex::encode(fingerprint),
+            "0954edd5e0afe5542a4adf012611a91912a3ec16"
+        );
+    }
+
+    // CryptoPals Set 6 Challenge 44
+    #[test]
+    fn test_dsa_repeated_nonce_recovery() {
+        let filename = "test_data/44.txt";
+        let contents = fs::read_to_string(filename).unwrap();
+        let messages = load_dsa_signed_messages(&contents).unwrap();
+        // Only 9 of the 11 entries of the published 44.txt. The signatures
+        // on "People em say ya come from Jamaica," and "But me born an'
+        // raised in the ghetto that I want yas to know," are missing, every
+        // entry here verifies under the published y. Add those two back and
+        // expect 11 once the original file is at hand.
+        assert_eq!(messages.len(), 9);
+        for message in messages.iter() {
+            assert_eq!(dsa_hash(message.message.as_bytes()), message.hash);
+        }
+        let params = DsaParameters::cryptopals();
+        let public = DsaPublicKey {
+            params: params.clone(),
+            y: BigUint::parse_bytes(b"2d026f4bf30195ede3a088da85e398ef869611d0f68f0713d51c9c1a3a26c95105d915e2d8cdf26d056b86b8a7b85519b1c23cc3ecdc6062650462e3063bd179c2a6581519f674a61f1d89a1fff27171ebc1b93d4dc57bceb7ae2430f98a6a4d83d8279ee65d71c1203d2c96d65ebbf7cce9d32971c3de5084cce04a2e147821", 16).unwrap(),
+        };
+        for message in messages.iter() {
+            assert!(dsa_verify(
+                message.message.as_bytes(),
+                &message.signature,
+                &public
+            ));
+        }
+        let (unrelated, _) = generate_dsa_keys(&params);
+        let compromised = recover_dsa_keys_from_repeated_nonces(&messages, &[unrelated, public]);
+        assert_eq!(compromised.len(), 1);
+        let fingerprint = sha1(format!("{:x}", compromised[0].x).as_bytes());
+        assert_eq!(
+            hex::encode(fingerprint),
+            "ca8f6f7c66fa362d40760d135b763eb8527d3d52"
+        );
+        assert!(load_dsa_signed_messages("msg: lonely line").is_err());
+    }
+
+    // CryptoPals Set 6 Challenge 45
+    #[test]
+    fn test_dsa_parameter_tampering() {
+        let params = DsaParameters::cryptopals();
+        let (public, private) = generate_dsa_keys(&params);
+        let messages = ["Hello, world".as_bytes(), "Goodbye, world".as_bytes()];
+        let signature = dsa_sign(messages[0], &private);
+        assert!(dsa_verify_strict(messages[0], &signature, &public));
+        assert!(dsa_verify_unchecked(messages[0], &signature, &public));
+
+        let zero_key = DsaPublicKey {
+            params: DsaParameters {
+                g: BigUint::from(0u32),
+                ..params.clone()
+            },
+            y: public.y.clone(),
+        };
+        let forged = dsa_zero_generator_signature(&params);
+        for message in messages.iter() {
+            assert!(dsa_verify_unchecked(message, &forged, &zero_key));
+            assert!(!dsa_verify(message, &forged, &zero_key));
+            assert!(!dsa_verify_strict(message, &forged, &zero_key));
+        }
+
+        let magic_key = DsaPublicKey {
+            params: DsaParameters {
+                g: &params.p + 1u32,
+                ..params.clone()
+            },
+            y: public.y.clone(),
+        };
+        let magic = dsa_magic_signature(&magic_key);
+        for message in messages.iter() {
+            assert!(dsa_verify_unchecked(message, &magic, &magic_key));
+            assert!(dsa_verify(message, &magic, &magic_key));
+            assert!(!dsa_verify_strict(message, &magic, &magic_key));
+        }
+    }
+
+    // CryptoPals Set 6 Challenge 46
+    #[test]
+    fn test_rsa_parity_attack() {
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let encoded = "VGhhdCdzIHdoeSBJIGZvdW5kIHlvdSBkb24ndCBwbGF5IGFyb3VuZCB3aXRoIHRoZSBGdW5reSBDb2xkIE1lZGluYQ==".to_string();
+        let plaintext = b64_decode(&encoded).unwrap();
+        let ciphertext = rsa_encrypt_bigint(&bytes_to_bigint(&plaintext), &public);
+        let mut updates: Vec<BigUint> = Vec::new();
+        let mut record = |bound: &BigUint| updates.push(bound.clone());
+        let result = rsa_parity_attack(
+            &ciphertext,
+            &public,
+            rsa_parity_oracle(private),
+            Some(&mut record),
+        );
+        assert_eq!(bigint_to_bytes(&result), plaintext);
+        assert_eq!(updates.len(), 1024);
+        assert!(updates.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(*updates.last().unwrap() >= result);
+    }
+
+    #[test]
+    fn test_pkcs1_v15_encryption() {
+        let (public, private) = generate_rsa_keys(512, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let message = "kick it, CC".as_bytes();
+        let ciphertext = pkcs1_v15_encrypt(message, &public).unwrap();
+        assert_eq!(pkcs1_v15_decrypt(&ciphertext, &private).unwrap(), message);
+        let block = pkcs1_v15_encryption_pad(message, 64).unwrap();
+        assert_eq!(block.len(), 64);
+        assert_eq!(&block[..2], &[0x00, 0x02]);
+        assert!(!block[2..64 - message.len() - 1].contains(&0x00));
+        assert_eq!(pkcs1_v15_encryption_unpad(&block).unwrap(), message);
+        assert!(pkcs1_v15_encryption_pad(&[0x41u8; 54], 64).is_err());
+        let mut short_padding = vec![0x00u8, 0x02, 0xff, 0x00];
+        short_padding.extend_from_slice(&[0x41u8; 60]);
+        assert!(pkcs1_v15_encryption_unpad(&short_padding).is_err());
+    }
+
+    // CryptoPals Set 6 Challenge 47
+    #[test]
+    fn test_bleichenbacher_simple() {
+        let (public, private) = generate_rsa_keys(256, &BigUint::from(3u32));
+        let message = "kick it, CC".as_bytes();
+        let ciphertext = bytes_to_bigint(&pkcs1_v15_encrypt(message, &public).unwrap());
+        let oracle = pkcs1_v15_padding_oracle(private);
+        assert!(oracle(&ciphertext));
+        let complete = bleichenbacher_attack(
+            &ciphertext,
+            &public,
+            &oracle,
+            BleichenbacherVariant::Complete,
+        )
+        .unwrap();
+        assert_eq!(
+            pkcs1_v15_encryption_unpad(&complete.block).unwrap(),
+            message
+        );
+        assert!(complete.oracle_calls > 0);
+
+        // The simple variant gives up when the interval splits, which happens
+        // now and then, so give it a few fresh paddings of the message.
+        let simple = (0..10)
+            .find_map(|_| {
+                let ciphertext = bytes_to_bigint(&pkcs1_v15_encrypt(message, &public).unwrap());
+                let result = bleichenbacher_attack(
+                    &ciphertext,
+                    &public,
+                    &oracle,
+                    BleichenbacherVariant::Simple,
+                );
+                if let Err(e) = result {
+                    assert_eq!(e, "Plaintext spread over several intervals");
+                }
+                result.ok()
+            })
+            .unwrap();
+        assert_eq!(pkcs1_v15_encryption_unpad(&simple.block).unwrap(), message);
+    }
+
+    // CryptoPals Set 6 Challenge 48
+    #[test]
+    fn test_bleichenbacher_complete() {
+        let (public, private) = generate_rsa_keys(768, &BigUint::from(3u32));
+        let message = "Bleichenbacher strikes again".as_bytes();
+        let ciphertext = bytes_to_bigint(&pkcs1_v15_encrypt(message, &public).unwrap());
+        let oracle = pkcs1_v15_padding_oracle(private);
+        let result = bleichenbacher_attack(
+            &ciphertext,
+            &public,
+            oracle,
+            BleichenbacherVariant::Complete,
+        )
+        .unwrap();
+        assert_eq!(pkcs1_v15_encryption_unpad(&result.block).unwrap(), message);
+    }
+
+    #[test]
+    fn test_rsa_oaep() {
+        // MGF1-SHA-1 test vector from the pyca/cryptography suite
+        let mask = mgf1_sha1("foo".as_bytes(), 3);
+        assert_eq!(hex::encode(mask), "1ac907");
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let message = "Optimal asymmetric encryption padding".as_bytes();
+        let ciphertext = rsa_oaep_encrypt(message, &[], &public).unwrap();
+        assert_eq!(
+            rsa_oaep_decrypt(&ciphertext, &[], &private).unwrap(),
+            message
+        );
+        assert!(rsa_oaep_decrypt(&ciphertext, "label".as_bytes(), &private).is_err());
+        let block = oaep_encode(message, &[], 128).unwrap();
+        assert_eq!(block.len(), 128);
+        assert_eq!(oaep_decode(&block, &[]).unwrap(), message);
+        let mut tampered = block.clone();
+        tampered[0] = 0x01;
+        assert_eq!(oaep_decode(&tampered, &[]), Err("Decryption error"));
+        assert!(oaep_encode(&[0x41u8; 87], &[], 128).is_err());
+    }
+
+    #[test]
+    fn test_manger_attack() {
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let message = "Uniform errors matter".as_bytes();
+        let ciphertext = bytes_to_bigint(&rsa_oaep_encrypt(message, &[], &public).unwrap());
+        let result = manger_attack(&ciphertext, &public, manger_oracle(private)).unwrap();
+        assert_eq!(oaep_decode(&result.block, &[]).unwrap(), message);
+        // log2(n) queries for step 3 plus at most n / B for step 2
+        assert!(result.oracle_calls < 1024 + 256 + 64);
+    }
+
+    #[test]
+    fn test_cbc_mac() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let iv = [7u8; 16];
+        for len in [0usize, 5, 16, 37, 48] {
+            let message = vec![0x41u8; len];
+            let mut encrypter =
+                Crypter::new(Cipher::aes_128_cbc(), Mode::Encrypt, key, Some(&iv)).unwrap();
+            let mut ciphertext = vec![0u8; len + 32];
+            let count = encrypter.update(&message, &mut ciphertext).unwrap();
+            let count = count + encrypter.finalize(&mut ciphertext[count..]).unwrap();
+            assert_eq!(cbc_mac(&message, key, &iv), ciphertext[count - 16..count]);
+        }
+    }
+
+    // CryptoPals Set 7 Challenge 49
+    #[test]
+    fn test_cbc_mac_iv_forgery() {
+        let key: [u8; 16] = rand::random();
+        let server = TransferServer::new(&key);
+        let (victim, attacker) = (2u32, 3u32);
+
+        let request = TransferClient::new(&key, attacker).sign_transfer(attacker, 1000000);
+        let forged = forge_transfer_with_iv(&request, victim).unwrap();
+        let transfer = server.process_transfer(&forged).unwrap();
+        assert_eq!(
+            transfer,
+            Transfer {
+                from: victim,
+                to: attacker,
+                amount: 1000000
+            }
+        );
+
+        let mut tampered = forged.clone();
+        tampered[0] ^= 1;
+        assert_eq!(server.process_transfer(&tampered), Err("Invalid MAC"));
+        assert!(forge_transfer_with_iv(&request, 12345).is_err());
+    }
+
+    // CryptoPals Set 7 Challenge 49
+    #[test]
+    fn test_cbc_mac_length_extension() {
+        let key: [u8; 16] = rand::random();
+        let server = TransferServer::new(&key);
+        let (victim, attacker) = (2u32, 3u32);
+
+        let victim_request =
+            TransferClient::new(&key, victim).sign_transfers(&[(5, 100), (7, 250)]);
+        assert_eq!(server.process_transfers(&victim_request).unwrap().len(), 2);
+        // The first block of the attacker's message gets scrambled so the
+        // first transaction is only filler
+        let attacker_request =
+            TransferClient::new(&key, attacker).sign_transfers(&[(0, 0), (attacker, 1000000)]);
+
+        let forged =
+            forge_transfers_with_length_extension(&victim_request, &attacker_request).unwrap();
+        let transfers = server.process_transfers(&forged).unwrap();
+        assert!(transfers.iter().all(|transfer| transfer.from == victim));
+        assert!(transfers.contains(&Transfer {
+            from: victim,
+            to: attacker,
+            amount: 1000000
+        }));
+    }
+
+    // CryptoPals Set 7 Challenge 50
+    #[test]
+    fn test_cbc_mac_hash_collision() {
+        let original = "alert('MZA who was that?');\n".as_bytes();
+        let hash = cbc_mac_hash(original);
+        assert_eq!(hex::encode(&hash), "296b8d7cb78a243dda4d0a61d33bbdd1");
+
+        let prefix = "alert('Ayo, the Wu is back!');//".as_bytes();
+        let forged = forge_cbc_mac_hash_collision(original, prefix).unwrap();
+        assert_eq!(cbc_mac_hash(&forged), hash);
+        assert!(forged.starts_with(prefix));
+        assert!(forged.ends_with("as that?');\n".as_bytes()));
+        let body = &forged[..forged.len() - 1];
+        assert!(body.iter().all(|byte| (0x20..0x7f).contains(byte)));
+    }
+
+    // CryptoPals Set 3 Challenge 18
+    #[test]
+    fn test_aes_ctr() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let ciphertext = b64_decode(
+            &"L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==".to_string(),
+        )
+        .unwrap();
+        let plaintext = aes_ctr(&ciphertext, key, 0);
+        assert_eq!(
+            String::from_utf8(plaintext.clone()).unwrap(),
+            "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+        );
+        assert_eq!(aes_ctr(&plaintext, key, 0), ciphertext);
+    }
+
+    // CryptoPals Set 7 Challenge 51
+    #[test]
+    fn test_compression_ratio_attack() {
+        let body = "sessionid=TmV2ZXIgcmV2".as_bytes();
+        let request = format_request(body, CRIME_SESSION_ID);
+        assert!(request.ends_with("Content-Length: 22\nsessionid=TmV2ZXIgcmV2".as_bytes()));
+        let wrong_guess = format_request("sessionid=XYZ!#$%&*<".as_bytes(), CRIME_SESSION_ID);
+        assert!(deflate(&request).len() < deflate(&wrong_guess).len());
+
+        let alphabet =
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=".as_bytes();
+        let known_prefix = "\nCookie: sessionid=".as_bytes();
+        // The filler is random, a few runs make sure no draw throws it off
+        for cipher in [CompressionCipher::Ctr, CompressionCipher::Cbc] {
+            let oracle = compression_oracle(CRIME_SESSION_ID, cipher);
+            for _ in 0..3 {
+                let session_id =
+                    compression_ratio_attack(&oracle, known_prefix, alphabet, b'\n', 64).unwrap();
+                assert_eq!(String::from_utf8(session_id).unwrap(), CRIME_SESSION_ID);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weak_hash() {
+        for len in 0..40 {
+            assert_eq!((len + weak_hash_padding(len).len()) % 16, 0);
+        }
+        let hash = WeakHash::new(&[0x12, 0x34]);
+        let digest = hash.hash("YELLOW SUBMARINE".as_bytes());
+        assert_eq!(digest.len(), 2);
+        assert_eq!(digest, hash.hash("YELLOW SUBMARINE".as_bytes()));
+        assert_ne!(
+            digest,
+            WeakHash::new(&[0x12, 0x35]).hash("YELLOW SUBMARINE".as_bytes())
+        );
+    }
+
+    // CryptoPals Set 7 Challenge 52
+    #[test]
+    fn test_joux_multicollision() {
+        let f = WeakHash::new(&[0x12, 0x34]);
+        let multicollision = joux_multicollision(&f, 4);
+        assert_eq!(multicollision.message_count(), 16);
+        let digest = f.hash(&multicollision.message(0));
+        let mut messages: Vec<Vec<u8>> = (0..16).map(|i| multicollision.message(i)).collect();
+        assert!(messages.iter().all(|message| f.hash(message) == digest));
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), 16);
+
+        let g = WeakHash::new(&[0x56, 0x78, 0x9a]);
+        let collision = find_cascade_collision(&f, &g);
+        let [first, second] = &collision.messages;
+        assert_ne!(first, second);
+        assert_eq!(cascade_hash(&f, &g, first), cascade_hash(&f, &g, second));
+        // A generic birthday attack on the 40 bit cascade needs around 2^20
+        assert!(collision.f_compressions + collision.g_compressions < 1 << 16);
+    }
+
+    // CryptoPals Set 7 Challenge 53
+    #[test]
+    fn test_kelsey_schneier_second_preimage() {
+        let hash = WeakHash::new(&[0x12, 0x34, 0x56]);
+        let expandable = ExpandableMessage::new(&hash, &hash.initial_state, 4);
+        assert_eq!((expandable.min_blocks(), expandable.max_blocks()), (4, 19));
+        for blocks in 4..=19 {
+            let message = expandable.message(blocks).unwrap();
+            assert_eq!(message.len(), blocks * 16);
+            assert_eq!(
+                hash.process(&hash.initial_state, &message),
+                expandable.final_state().unwrap()
+            );
+        }
+        assert!(expandable.message(20).is_err());
+
+        let k = 8;
+        let mut message: Vec<u8> = (0..(1 << k) * 16 + 16).map(|i| (i % 251) as u8).collect();
+        message.extend("and a partial block".as_bytes());
+        let preimage = kelsey_schneier_second_preimage(&hash, &message, k).unwrap();
+        assert_ne!(preimage.message, message);
+        assert_eq!(preimage.message.len(), message.len());
+        assert_eq!(hash.hash(&preimage.message), hash.hash(&message));
+        // Brute force would need around 2^24 compressions
+        assert!(preimage.expandable_compressions + preimage.bridge_compressions < 1 << 20);
+    }
+
+    // CryptoPals Set 7 Challenge 54
+    #[test]
+    fn test_nostradamus_herding() {
+        let hash = WeakHash::new(&[0x12, 0x34]);
+        let diamond = DiamondStructure::new(&hash, 6);
+        assert_eq!(diamond.levels[0].len(), 64);
+        for leaf in [0usize, 17, 63] {
+            let path = diamond.path(leaf);
+            assert_eq!(
+                hash.process(&diamond.levels[0][leaf], &path),
+                diamond.root()
+            );
+        }
+
+        let path = std::env::temp_dir().join("cryptopals_diamond_structure.bin");
+        let path = path.to_str().unwrap();
+        diamond.save(path).unwrap();
+        let diamond = DiamondStructure::load(&hash, path).unwrap();
+        fs::remove_file(path).unwrap();
+        let mut corrupted = diamond.to_bytes();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(DiamondStructure::from_bytes(&hash, &corrupted).is_err());
+        for k in [62, 63, 255] {
+            let mut header = diamond.to_bytes();
+            header[1] = k;
+            assert!(DiamondStructure::from_bytes(&hash, &header).is_err());
+        }
+
+        let commitment = diamond.commitment(&hash, 2);
+        let prediction = "Ravens 27, 49ers 24, on the 3rd.".as_bytes();
+        assert_eq!(prediction.len(), 32);
+        let herded = diamond.herd(&hash, prediction).unwrap();
+        assert!(herded.message.starts_with(prediction));
+        assert_eq!(hash.hash(&herded.message), commitment);
+        assert!(diamond.herd(&hash, "not a block".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_md4() {
+        // RFC 1320 test suite
+        let vectors = [
+            ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ];
+        for (message, digest) in vectors {
+            assert_eq!(hex::encode(md4(message.as_bytes())), digest);
+        }
+        let block = [0x61u8; 64];
+        assert_eq!(md4_block(&md4_words(&block)), block);
+        assert_eq!(md4_trace(&MD4_INITIAL_STATE, &md4_words(&block)).len(), 52);
+    }
+
+    // CryptoPals Set 7 Challenge 55
+    #[test]
+    fn test_wang_md4_collision() {
+        let collision = wang_md4_collision(1 << 24).unwrap();
+        let [first, second] = &collision.messages;
+        assert_ne!(first, second);
+        assert_eq!(md4(first), md4(second));
+        let words = md4_words(first);
+        assert_eq!(md4_words(second), wang_differential(&words));
+        assert!(wang_round_conditions_hold(&words, 0));
+    }
+
+    #[test]
+    fn test_rc4() {
+        let vectors = [
+            ("Key", "Plaintext", "bbf316e8d940af0ad3"),
+            ("Wiki", "pedia", "1021bf0420"),
+            ("Secret", "Attack at dawn", "45a01f645fc35b383552544b9bf5"),
+        ];
+        for (key, plaintext, ciphertext) in vectors {
+            let encrypted = rc4(plaintext.as_bytes(), key.as_bytes()).unwrap();
+            assert_eq!(hex::encode(&encrypted), ciphertext);
+            assert_eq!(
+                rc4(&encrypted, key.as_bytes()).unwrap(),
+                plaintext.as_bytes()
+            );
+        }
+        assert!(rc4(b"Plaintext", b"").is_err());
+        let mut cipher = Rc4::new(b"Key").unwrap();
+        let mut split = cipher.apply(b"Plain");
+        split.extend(cipher.apply(b"text"));
+        assert_eq!(hex::encode(split), "bbf316e8d940af0ad3");
+    }
+
+    #[test]
+    fn test_rc4_bias_attack_progress() {
+        let oracle = rc4_cookie_oracle(b"ab");
+        let reported = AtomicUsize::new(0);
+        let progress = |done: usize, total: usize| {
+            assert_eq!(total, 4 << 14);
+            reported.fetch_max(done, Ordering::Relaxed);
+        };
+        let recovered = rc4_bias_attack(&oracle, 2, 1 << 14, 3, progress).unwrap();
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(reported.into_inner(), 4 << 14);
+        assert!(rc4_bias_attack(&oracle, 33, 1, 1, |_, _| ()).is_err());
+        assert!(rc4_bias_attack(&oracle, 2, 1, 0, |_, _| ()).is_err());
+    }
+
+    // CryptoPals Set 7 Challenge 56
+    #[test]
+    fn test_rc4_bias_attack_single_byte() {
+        // The byte goes under keystream bytes 16 and 32, 2^23 samples each
+        // put the right guess about six standard deviations ahead.
+        let oracle = rc4_cookie_oracle(b"Q");
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let recovered = rc4_bias_attack(oracle, 1, 1 << 23, threads, |_, _| ()).unwrap();
+        assert_eq!(recovered, b"Q");
+    }
+
+    // 32 request lengths of 2^25 encryptions each, about a billion in total.
+    // Run it with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_rc4_bias_attack() {
+        let cookie = b64_decode(&RC4_COOKIE.to_string()).unwrap();
+        let oracle = rc4_cookie_oracle(&cookie);
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let progress = |done: usize, total: usize| {
+            if done.is_multiple_of(1 << 24) {
+                println!("{}/{} encryptions", done, total);
+            }
+        };
+        let recovered = rc4_bias_attack(oracle, cookie.len(), 1 << 25, threads, progress).unwrap();
+        assert_eq!(recovered, cookie);
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test cases 1, 2 and 6
+        let long_key = [0xaau8; 131];
+        let vectors: [(&[u8], &[u8], &str); 3] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &long_key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ];
+        for (key, message, mac) in vectors {
+            assert_eq!(hex::encode(hmac_sha256(key, message)), mac);
+        }
+    }
+
+    #[test]
+    fn test_trial_division() {
+        let n = BigUint::from(2u32).pow(3) * 3u32 * 101u32 * BigUint::from(1_000_003u32);
+        let (factors, cofactor) = trial_division(&n, 1000);
+        assert_eq!(factors, vec![(2, 3), (3, 1), (101, 1)]);
+        assert_eq!(cofactor, BigUint::from(1_000_003u32));
+        let (factors, cofactor) = trial_division(&BigUint::from(97u32 * 89), 100);
+        assert_eq!(factors, vec![(89, 1), (97, 1)]);
+        assert_eq!(cofactor, BigUint::from(1u32));
+    }
+
+    // CryptoPals Set 8 Challenge 57
+    #[test]
+    fn test_subgroup_confinement_attack() {
+        let params = DhParameters::challenge_57();
+        let (alice_public, alice_private) = generate_dh_keys(&params);
+        let (bob_public, bob_private) = generate_dh_keys(&params);
+        assert_eq!(
+            dh_shared_secret(&alice_private, &bob_public.y),
+            dh_shared_secret(&bob_private, &alice_public.y)
+        );
+
+        let h = element_of_order(&params.p, 7963).unwrap();
+        assert_eq!(
+            mod_exp(&h, &BigUint::from(7963u32), &params.p),
+            BigUint::from(1u32)
+        );
+        assert!(element_of_order(&params.p, 7).is_err());
+
+        let victim = dh_mac_victim(&bob_private);
+        let (x, modulus) = subgroup_confinement_attack(&params, victim, 1 << 16).unwrap();
+        assert!(modulus > params.q);
+        assert_eq!(x, bob_private.x);
+    }
+
+    fn solve_dlog_in_range(solver: &impl DiscreteLogSolver, bits: u32) {
+        let params = DhParameters::challenge_58();
+        let group = MultiplicativeGroup {
+            p: params.p.clone(),
+        };
+        let high = BigUint::from(1u64 << bits);
+        let x = random_range(&BigUint::from(0u32), &high);
+        let y = group.power(&params.g, &x);
+        let found = solver.solve(&group, &params.g, &y, &BigUint::from(0u32), &high);
+        assert_eq!(found, Some(x.clone()));
+
+        let low = &x - (&x >> 2u32);
+        let found = solver.solve(&group, &params.g, &y, &low, &high);
+        assert_eq!(found, Some(x.clone()));
+        let found = solver.solve(&group, &params.g, &y, &(&x + 1u32), &high);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn test_baby_step_giant_step() {
+        solve_dlog_in_range(&BabyStepGiantStep, 20);
+    }
+
+    // CryptoPals Set 8 Challenge 58
+    #[test]
+    fn test_kangaroo() {
+        solve_dlog_in_range(&Kangaroo::new(), 20);
+        solve_dlog_in_range(&Kangaroo::with_parameters(14, 1 << 14), 20);
+    }
+
+    // CryptoPals Set 8 Challenge 58
+    #[test]
+    fn test_recover_dh_private_key() {
+        let params = DhParameters::challenge_58();
+        let (public, private) = generate_dh_keys(&params);
+        let victim = dh_mac_victim(&private);
+        let (_, modulus) = subgroup_confinement_attack(&params, &victim, 1 << 16).unwrap();
+        assert!(modulus < params.q);
+        let x = recover_dh_private_key(&public, &victim, 1 << 16, &Kangaroo::new()).unwrap();
+        assert_eq!(x, private.x);
+    }
+
+    #[test]
+    fn test_ec_arithmetic() {
+        for curve in [
+            WeierstrassCurve::cryptopals(),
+            WeierstrassCurve::p256(),
+            WeierstrassCurve::secp256k1(),
+        ] {
+            assert!(curve.is_on_curve(&curve.g));
+            assert_eq!(curve.scalar_mul(&curve.g, &curve.n), EcPoint::Infinity);
+            let two_g = curve.double(&curve.g);
+            assert!(curve.is_on_curve(&two_g));
+            assert_eq!(
+                curve.add(&two_g, &curve.g),
+                curve.scalar_mul(&curve.g, &BigUint::from(3u32))
+            );
+            assert_eq!(
+                curve.add(&curve.g, &curve.negate(&curve.g)),
+                EcPoint::Infinity
+            );
+            assert_eq!(curve.add(&EcPoint::Infinity, &curve.g), curve.g);
+        }
+
+        let curve = WeierstrassCurve::p256();
+        let two_g = EcPoint::new(
+            BigUint::parse_bytes(
+                b"7cf27b188d034f7e8a52380304b51ac3c08969e277f21b35a60b48fc47669978",
+                16,
+            )
+            .unwrap(),
+            BigUint::parse_bytes(
+                b"07775510db8ed040293d9ac69f7430dbba7dade63ce982299e04b79d227873d1",
+                16,
+            )
+            .unwrap(),
+        );
+        assert_eq!(curve.double(&curve.g), two_g);
+        assert!(!curve.is_on_curve(&EcPoint::new(BigUint::from(1u32), BigUint::from(1u32))));
+    }
+
+    #[test]
+    fn test_ecdh() {
+        for curve in [WeierstrassCurve::cryptopals(), WeierstrassCurve::p256()] {
+            let (alice_public, alice_private) = generate_ec_keys(&curve);
+            let (bob_public, bob_private) = generate_ec_keys(&curve);
+            let alice_secret = ecdh_shared_secret(&alice_private, &bob_public.point).unwrap();
+            let bob_secret = ecdh_shared_secret(&bob_private, &alice_public.point).unwrap();
+            assert_eq!(alice_secret, bob_secret);
+            let bogus = EcPoint::new(BigUint::from(1u32), BigUint::from(1u32));
+            assert!(ecdh_shared_secret(&alice_private, &bogus).is_err());
+            assert!(ecdh_shared_secret(&alice_private, &EcPoint::Infinity).is_err());
+        }
+    }
+
+    #[test]
+    fn test_mod_sqrt() {
+        // p = 1 mod 8 takes the full Tonelli-Shanks loop
+        for p in [13u32, 17, 233, 7681] {
+            let p = BigUint::from(p);
+            let mut residues = 0;
+            for a in 1..p.to_u32_digits()[0] {
+                let a = BigUint::from(a);
+                if let Some(root) = mod_sqrt(&a, &p) {
+                    assert_eq!(&root * &root % &p, a);
+                    residues += 1;
+                }
+            }
+            assert_eq!(BigUint::from(residues * 2 + 1u32), p);
+        }
+        let curve = WeierstrassCurve::cryptopals();
+        assert!(curve.is_on_curve(&curve.random_point()));
+    }
+
+    // CryptoPals Set 8 Challenge 59
+    #[test]
+    fn test_invalid_curve_attack() {
+        let curve = WeierstrassCurve::cryptopals();
+        let (b, order) = INVALID_CURVES[0];
+        let invalid = WeierstrassCurve {
+            b: BigUint::from(b),
+            ..curve.clone()
+        };
+        let point = point_of_order(
+            &invalid,
+            &BigUint::parse_bytes(order.as_bytes(), 10).unwrap(),
+            89,
+        )
+        .unwrap();
+        assert!(invalid.is_on_curve(&point));
+        assert!(!curve.is_on_curve(&point));
+        assert_eq!(
+            curve.scalar_mul(&point, &BigUint::from(89u32)),
+            EcPoint::Infinity
+        );
+
+        let (_, private) = generate_ec_keys(&curve);
+        let d = invalid_curve_attack(&curve, ecdh_mac_victim_unchecked(&private), 1 << 16).unwrap();
+        assert_eq!(d, private.d);
+        assert!(invalid_curve_attack(&curve, ecdh_mac_victim(&private), 1 << 16).is_err());
+    }
+
+    #[test]
+    fn test_montgomery_ladder() {
+        let curve = MontgomeryCurve::cryptopals();
+        let weierstrass = curve.to_weierstrass();
+        assert_eq!(weierstrass, WeierstrassCurve::cryptopals());
+        assert!(curve.is_on_curve(&curve.u));
+        assert_eq!(curve.ladder(&curve.u, &curve.n), BigUint::from(0u32));
+
+        let key = generate_montgomery_key(&curve);
+        let point = weierstrass.scalar_mul(&weierstrass.g, &key.d);
+        let (u, v) = curve.to_montgomery_point(&point).unwrap();
+        assert_eq!(u, key.u);
+        assert_eq!(curve.to_weierstrass_point(&u, &v), point);
+        assert_eq!(curve.to_montgomery_point(&EcPoint::Infinity), None);
+
+        let other = generate_montgomery_key(&curve);
+        assert_eq!(
+            curve.ladder(&key.u, &other.d),
+            curve.ladder(&other.u, &key.d)
+        );
+        assert_eq!(
+            curve.twist_order(),
+            BigUint::parse_bytes(b"233970423115425145549737651362517029924", 10).unwrap()
+        );
+    }
+
+    // CryptoPals Set 8 Challenge 60
+    #[test]
+    fn test_twist_attack() {
+        let curve = MontgomeryCurve::cryptopals();
+        // The factors below 2^17 cover 46 bits, with a 64 bit key kangaroo
+        // only has 18 bits left to search.
+        let high = BigUint::from(u64::MAX);
+        let d = random_range(&BigUint::from(1u32), &(&high / 11u32));
+        // A key divisible by the smallest factor, 11, gives a zero residue
+        for d in [&d * 11u32 + 1u32, d * 11u32] {
+            let key = MontgomeryPrivateKey::new(&curve, d);
+            let victim = montgomery_mac_victim(&key);
+            let d = twist_attack(&curve, &key.u, victim, 1 << 17, &high, &Kangaroo::new()).unwrap();
+            assert_eq!(d, key.d);
+        }
+    }
+
+    #[test]
+    fn test_ecdsa() {
+        for curve in [WeierstrassCurve::cryptopals(), WeierstrassCurve::p256()] {
+            let (public, private) = generate_ec_keys(&curve);
+            let signature = ecdsa_sign(b"hi mom", &private);
+            assert!(ecdsa_verify(b"hi mom", &signature, &public));
+            assert!(!ecdsa_verify(b"hi dad", &signature, &public));
+            let (other, _) = generate_ec_keys(&curve);
+            assert!(!ecdsa_verify(b"hi mom", &signature, &other));
+            let zero = EcdsaSignature {
+                r: BigUint::from(0u32),
+                s: signature.s.clone(),
+            };
+            assert!(!ecdsa_verify(b"hi mom", &zero, &public));
+        }
+    }
+
+    #[test]
+    fn test_smooth_prime_discrete_log() {
+        assert_eq!(primes_below(30), vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        let (p, factors) = generate_smooth_prime(256, &[3, 5]);
+        assert_eq!(p.bits(), 256);
+        assert!(is_probable_prime(&p, 40));
+        assert!(!factors.contains(&3) && !factors.contains(&5));
+        let order: BigUint = factors.iter().map(|r| BigUint::from(*r)).product();
+        assert_eq!(order, &p - 1u32);
+
+        // 2^x for a random x, the log comes back mod the order of 2
+        let group = MultiplicativeGroup { p: p.clone() };
+        let base = BigUint::from(2u32);
+        let x = random_range(&BigUint::from(0u32), &p);
+        let target = group.power(&base, &x);
+        let log = pohlig_hellman(&group, &base, &target, &factors, &BabyStepGiantStep).unwrap();
+        assert_eq!(group.power(&base, &log), target);
+    }
+
+    // CryptoPals Set 8 Challenge 61
+    #[test]
+    fn test_ecdsa_duplicate_signature_key() {
+        let curve = WeierstrassCurve::p256();
+        let (public, private) = generate_ec_keys(&curve);
+        let message = b"I, the owner of this key, wrote this";
+        let signature = ecdsa_sign(message, &private);
+        let forged = ecdsa_duplicate_signature_key(message, &signature, &public).unwrap();
+        let forged_public = forged.public_key();
+        assert_ne!(forged_public.point, public.point);
+        assert!(ecdsa_verify(message, &signature, &forged_public));
+        assert!(ecdsa_verify(
+            b"another message",
+            &ecdsa_sign(b"another message", &forged),
+            &forged_public
+        ));
+    }
+
+    // CryptoPals Set 8 Challenge 61
+    #[test]
+    fn test_pkcs1_v15_duplicate_signature_key() {
+        let (public, private) = generate_rsa_keys(1024, &BigUint::from(RSA_DEFAULT_EXPONENT));
+        let message = b"I, the owner of this key, wrote this";
+        let signature = pkcs1_v15_sign(message, &private, HashAlgorithm::Sha256).unwrap();
+        let forged =
+            pkcs1_v15_duplicate_signature_key(message, &signature, &public, HashAlgorithm::Sha256)
+                .unwrap();
+        assert_ne!(forged.n, public.n);
+        assert_eq!(forged.size(), public.size());
+        assert!(pkcs1_v15_verify(
+            message,
+            &signature,
+            &forged,
+            HashAlgorithm::Sha256
+        ));
+        assert!(!pkcs1_v15_verify(
+            b"another message",
+            &signature,
+            &forged,
+            HashAlgorithm::Sha256
+        ));
+    }
 }
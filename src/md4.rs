@@ -0,0 +1,408 @@
+use rand::{thread_rng, Rng};
+
+pub const MD4_DIGEST_SIZE: usize = 16usize;
+pub const MD4_BLOCK_SIZE: usize = 64usize;
+pub const MD4_INITIAL_STATE: [u32; 4] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476];
+
+const ROUND_CONSTANTS: [u32; 3] = [0x00000000, 0x5A827999, 0x6ED9EBA1];
+const ROUND_SHIFTS: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+const ROUND_WORDS: [[usize; 16]; 3] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+    [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+];
+
+// The three round functions
+pub fn md4_f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub fn md4_g(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+pub fn md4_h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+/// Merkle-Damgard padding like SHA-1's, except that the length is
+/// little-endian.
+pub fn md4_padding(message_len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80u8];
+    let zeros = (MD4_BLOCK_SIZE + 55 - message_len % MD4_BLOCK_SIZE) % MD4_BLOCK_SIZE;
+    padding.extend(vec![0u8; zeros]);
+    padding.extend_from_slice(&((message_len as u64) * 8).to_le_bytes());
+    padding
+}
+
+pub fn md4_words(block: &[u8]) -> [u32; 16] {
+    let mut words = [0u32; 16];
+    for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+pub fn md4_block(words: &[u32; 16]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+// Round, message word index, shift and constant of one of the 48 steps.
+fn step_parameters(step: usize) -> (usize, usize, u32, u32) {
+    let (round, i) = (step / 16, step % 16);
+    (
+        round,
+        ROUND_WORDS[round][i],
+        ROUND_SHIFTS[round][i % 4],
+        ROUND_CONSTANTS[round],
+    )
+}
+
+// The step without the message word: Q[i - 4] + f(Q[i - 1], Q[i - 2], Q[i - 3]) + K.
+// trace[i] is Q[i - 4], see md4_trace.
+fn step_input(step: usize, trace: &[u32]) -> u32 {
+    let (round, _, _, constant) = step_parameters(step);
+    let (x, y, z) = (trace[step + 3], trace[step + 2], trace[step + 1]);
+    let f = match round {
+        0 => md4_f(x, y, z),
+        1 => md4_g(x, y, z),
+        _ => md4_h(x, y, z),
+    };
+    trace[step].wrapping_add(f).wrapping_add(constant)
+}
+
+/// The value step `step` (0 to 47) writes, given the trace of the steps
+/// before it.
+pub fn md4_step(step: usize, trace: &[u32], words: &[u32; 16]) -> u32 {
+    let (_, word, shift, _) = step_parameters(step);
+    step_input(step, trace)
+        .wrapping_add(words[word])
+        .rotate_left(shift)
+}
+
+/// Invert a step: the message word that makes step `step` write `value`.
+pub fn md4_step_word(step: usize, trace: &[u32], value: u32) -> u32 {
+    let (_, _, shift, _) = step_parameters(step);
+    value
+        .rotate_right(shift)
+        .wrapping_sub(step_input(step, trace))
+}
+
+/// Every register value of the compression function. The first four
+/// entries are the a, d, c and b of the input state and entry i + 4 is the
+/// value step i writes, so step i updates the register last written by
+/// entry i and reads entries i + 1 to i + 3. In Wang's naming entries 4 to 7
+/// are a1, d1, c1 and b1, entries 8 to 11 are a2, d2, c2 and b2 and so on.
+pub fn md4_trace(state: &[u32; 4], words: &[u32; 16]) -> Vec<u32> {
+    let mut trace = vec![state[0], state[3], state[2], state[1]];
+    for step in 0..48 {
+        let value = md4_step(step, &trace, words);
+        trace.push(value);
+    }
+    trace
+}
+
+pub fn md4_compress(state: &mut [u32; 4], block: &[u8]) {
+    let trace = md4_trace(state, &md4_words(block));
+    // The last values written to a, b, c and d
+    let last = [trace[48], trace[51], trace[50], trace[49]];
+    for (value, new) in state.iter_mut().zip(last) {
+        *value = value.wrapping_add(new);
+    }
+}
+
+/// Calculate the MD4 digest of a message.
+pub fn md4(message: &[u8]) -> Vec<u8> {
+    let mut state = MD4_INITIAL_STATE;
+    let mut padded = message.to_vec();
+    padded.extend(md4_padding(message.len()));
+    for block in padded.chunks(MD4_BLOCK_SIZE) {
+        md4_compress(&mut state, block);
+    }
+    state.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitCondition {
+    Zero,
+    One,
+    /// Equal to the same bit of the value written this many steps earlier.
+    Equal(usize),
+    /// Different from the same bit of the value written this many steps
+    /// earlier.
+    Differ(usize),
+}
+
+use BitCondition::{Differ, Equal, One, Zero};
+
+/// Wang et al.'s sufficient conditions for the MD4 collision differential,
+/// as (step, bit, condition). Bits are numbered from 1 like in the paper.
+pub const WANG_CONDITIONS: &[(usize, u32, BitCondition)] = &[
+    // a1
+    (0, 7, Equal(1)),
+    // d1
+    (1, 7, Zero),
+    (1, 8, Equal(1)),
+    (1, 11, Equal(1)),
+    // c1
+    (2, 7, One),
+    (2, 8, One),
+    (2, 11, Zero),
+    (2, 26, Equal(1)),
+    // b1
+    (3, 7, One),
+    (3, 8, Zero),
+    (3, 11, Zero),
+    (3, 26, Zero),
+    // a2
+    (4, 8, One),
+    (4, 11, One),
+    (4, 26, Zero),
+    (4, 14, Equal(1)),
+    // d2
+    (5, 14, Zero),
+    (5, 19, Equal(1)),
+    (5, 20, Equal(1)),
+    (5, 21, Equal(1)),
+    (5, 22, Equal(1)),
+    (5, 26, One),
+    // c2
+    (6, 13, Equal(1)),
+    (6, 14, Zero),
+    (6, 15, Equal(1)),
+    (6, 19, Zero),
+    (6, 20, Zero),
+    (6, 21, One),
+    (6, 22, Zero),
+    // b2
+    (7, 13, One),
+    (7, 14, One),
+    (7, 15, Zero),
+    (7, 17, Equal(1)),
+    (7, 19, Zero),
+    (7, 20, Zero),
+    (7, 21, Zero),
+    (7, 22, Zero),
+    // a3
+    (8, 13, One),
+    (8, 14, One),
+    (8, 15, One),
+    (8, 17, Zero),
+    (8, 19, Zero),
+    (8, 20, Zero),
+    (8, 21, Zero),
+    (8, 22, One),
+    (8, 23, Equal(1)),
+    (8, 26, Equal(1)),
+    // d3
+    (9, 13, One),
+    (9, 14, One),
+    (9, 15, One),
+    (9, 17, Zero),
+    (9, 20, Zero),
+    (9, 21, One),
+    (9, 22, One),
+    (9, 23, Zero),
+    (9, 26, One),
+    (9, 30, Equal(1)),
+    // c3
+    (10, 17, One),
+    (10, 20, Zero),
+    (10, 21, Zero),
+    (10, 22, Zero),
+    (10, 23, Zero),
+    (10, 26, Zero),
+    (10, 30, One),
+    (10, 32, Equal(1)),
+    // b3
+    (11, 20, Zero),
+    (11, 21, One),
+    (11, 22, One),
+    (11, 23, Equal(1)),
+    (11, 26, One),
+    (11, 30, Zero),
+    (11, 32, Zero),
+    // a4
+    (12, 23, Zero),
+    (12, 26, Zero),
+    (12, 27, Equal(1)),
+    (12, 29, Equal(1)),
+    (12, 30, One),
+    (12, 32, Zero),
+    // d4
+    (13, 23, Zero),
+    (13, 26, Zero),
+    (13, 27, One),
+    (13, 29, One),
+    (13, 30, Zero),
+    (13, 32, One),
+    // c4
+    (14, 19, Equal(1)),
+    (14, 23, One),
+    (14, 26, One),
+    (14, 27, Zero),
+    (14, 29, Zero),
+    (14, 30, Zero),
+    // b4
+    (15, 19, Zero),
+    (15, 26, One),
+    (15, 27, One),
+    (15, 29, One),
+    (15, 30, Zero),
+    // a5
+    (16, 19, Equal(2)),
+    (16, 26, One),
+    (16, 27, Zero),
+    (16, 29, One),
+    (16, 32, One),
+    // d5
+    (17, 19, Equal(1)),
+    (17, 26, Equal(2)),
+    (17, 27, Equal(2)),
+    (17, 29, Equal(2)),
+    (17, 32, Equal(2)),
+    // c5
+    (18, 26, Equal(1)),
+    (18, 27, Equal(1)),
+    (18, 29, Equal(1)),
+    (18, 30, Equal(1)),
+    (18, 32, Equal(1)),
+    // b5
+    (19, 29, Equal(1)),
+    (19, 30, One),
+    (19, 32, Zero),
+    // a6
+    (20, 29, One),
+    (20, 32, One),
+    // d6
+    (21, 29, Equal(2)),
+    // c6
+    (22, 29, Equal(1)),
+    (22, 30, Differ(1)),
+    (22, 32, Differ(1)),
+    // b9
+    (35, 32, One),
+    // a10
+    (36, 32, One),
+];
+
+// Which value the condition wants for the bit, given the trace up to the
+// step.
+fn wanted_bit(trace: &[u32], step: usize, bit: u32, condition: BitCondition) -> u32 {
+    let mask = 1u32 << (bit - 1);
+    match condition {
+        Zero => 0,
+        One => mask,
+        Equal(back) => trace[step + 4 - back] & mask,
+        Differ(back) => !trace[step + 4 - back] & mask,
+    }
+}
+
+fn conditions_for(step: usize) -> impl Iterator<Item = &'static (usize, u32, BitCondition)> {
+    WANG_CONDITIONS.iter().filter(move |(s, _, _)| *s == step)
+}
+
+/// Check the sufficient conditions of one round (0 to 2).
+pub fn wang_round_conditions_hold(words: &[u32; 16], round: usize) -> bool {
+    let trace = md4_trace(&MD4_INITIAL_STATE, words);
+    WANG_CONDITIONS
+        .iter()
+        .filter(|(step, _, _)| step / 16 == round)
+        .all(|(step, bit, condition)| {
+            let mask = 1u32 << (bit - 1);
+            trace[step + 4] & mask == wanted_bit(&trace, *step, *bit, *condition)
+        })
+}
+
+/// The second message of Wang's differential: M' = M + 2^31 in word 1,
+/// + 2^31 - 2^28 in word 2 and - 2^16 in word 12.
+pub fn wang_differential(words: &[u32; 16]) -> [u32; 16] {
+    let mut other = *words;
+    other[1] = other[1].wrapping_add(1 << 31);
+    other[2] = other[2].wrapping_add((1u32 << 31).wrapping_sub(1 << 28));
+    other[12] = other[12].wrapping_sub(1 << 16);
+    other
+}
+
+// Single-step modification: every round one value is forced to meet its
+// conditions and the message word is solved for it.
+fn round_one_modifications(words: &mut [u32; 16]) -> Vec<u32> {
+    let mut trace = md4_trace(&MD4_INITIAL_STATE, words);
+    for step in 0..16 {
+        let mut value = md4_step(step, &trace[..step + 4], words);
+        for (_, bit, condition) in conditions_for(step) {
+            let mask = 1u32 << (bit - 1);
+            value = (value & !mask) | wanted_bit(&trace, step, *bit, *condition);
+        }
+        words[step] = md4_step_word(step, &trace[..step + 4], value);
+        trace[step + 4] = value;
+    }
+    trace
+}
+
+// Multi-step modification for a round two value. Its message word also
+// feeds round one step `word`, so flip the bit of that round one value
+// which ends up at `bit` of the round two value, then solve the message
+// words of the four following steps so nothing else in round one changes.
+fn round_two_modification(words: &mut [u32; 16], trace: &mut [u32], step: usize, bit: u32) {
+    let (_, word, shift, _) = step_parameters(step);
+    let (_, _, round_one_shift, _) = step_parameters(word);
+    // A change of 2^(bit - shift) in the word moves the round one value by
+    // 2^(bit - shift + round_one_shift)
+    let flipped = (bit - 1 + 32 - shift + round_one_shift) % 32;
+    trace[word + 4] ^= 1u32 << flipped;
+    for round_one_step in word..word + 5 {
+        words[round_one_step] = md4_step_word(round_one_step, trace, trace[round_one_step + 4]);
+    }
+}
+
+fn round_two_modifications(words: &mut [u32; 16], trace: &mut Vec<u32>) {
+    // Only a5 and d5, correcting the later values would break the
+    // conditions of round one again
+    for step in [16usize, 17] {
+        for (_, bit, condition) in conditions_for(step) {
+            let mask = 1u32 << (bit - 1);
+            if trace[step + 4] & mask != wanted_bit(trace, step, *bit, *condition) {
+                round_two_modification(words, trace, step, *bit);
+                *trace = md4_trace(&MD4_INITIAL_STATE, words);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Md4Collision {
+    pub messages: [Vec<u8>; 2],
+    pub attempts: usize,
+}
+
+/// Wang's collision attack on MD4. Random blocks are massaged with single
+/// and multi-step message modifications until they meet the sufficient
+/// conditions of the first round and most of the second, the remaining
+/// conditions then hold by chance often enough to find a pair of single
+/// block messages with the same digest after some tens of thousands of
+/// attempts.
+pub fn wang_md4_collision(max_attempts: usize) -> Result<Md4Collision, &'static str> {
+    let mut rng = thread_rng();
+    for attempts in 1..=max_attempts {
+        let mut words: [u32; 16] = rng.gen();
+        let mut trace = round_one_modifications(&mut words);
+        round_two_modifications(&mut words, &mut trace);
+        if !wang_round_conditions_hold(&words, 0) {
+            continue;
+        }
+
+        let other = wang_differential(&words);
+        let (first, second) = (md4_block(&words), md4_block(&other));
+        let (mut first_state, mut second_state) = (MD4_INITIAL_STATE, MD4_INITIAL_STATE);
+        md4_compress(&mut first_state, &first);
+        md4_compress(&mut second_state, &second);
+        if first_state == second_state {
+            return Ok(Md4Collision {
+                messages: [first, second],
+                attempts,
+            });
+        }
+    }
+    Err("No collision found within the attempts")
+}
@@ -0,0 +1,574 @@
+use crate::bigint::{
+    bigint_to_bytes_padded, bytes_to_bigint, chinese_remainder, generate_smooth_prime, kth_root,
+    mod_exp, mod_inverse, random_range, BigUint,
+};
+use crate::dlog::{pohlig_hellman, BabyStepGiantStep, MultiplicativeGroup};
+use crate::rsa::{rsa_decrypt_bigint_crt, rsa_encrypt_bigint, RsaPrivateKey, RsaPublicKey};
+use crate::sha1::{sha1, SHA1_DIGEST_SIZE};
+use crate::xor::fixed_xor;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use openssl::sha::sha256;
+use rand::{thread_rng, Rng};
+
+// ASN.1 DER encoded DigestInfo structures up to and including the header
+// of the OCTET STRING holding the hash, as listed in RFC 8017 section 9.2.
+const SHA1_DIGEST_INFO: [u8; 15] = [
+    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14,
+];
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha1 => sha1(message),
+            HashAlgorithm::Sha256 => sha256(message).to_vec(),
+        }
+    }
+
+    pub fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            HashAlgorithm::Sha1 => &SHA1_DIGEST_INFO,
+            HashAlgorithm::Sha256 => &SHA256_DIGEST_INFO,
+        }
+    }
+
+    /// DER encoded DigestInfo of the message's hash.
+    pub fn digest_info(&self, message: &[u8]) -> Vec<u8> {
+        let mut info = self.digest_info_prefix().to_vec();
+        info.extend(self.digest(message));
+        info
+    }
+}
+
+/// EMSA-PKCS1-v1_5 encoding: 00 01 FF .. FF 00 DigestInfo, as long as the
+/// modulus.
+fn pkcs1_v15_signature_block(
+    message: &[u8],
+    hash: HashAlgorithm,
+    len: usize,
+) -> Result<Vec<u8>, &'static str> {
+    let digest_info = hash.digest_info(message);
+    if len < digest_info.len() + 11 {
+        return Err("Modulus is too small for the digest");
+    }
+    let mut block = vec![0x00u8, 0x01u8];
+    block.extend(vec![0xffu8; len - digest_info.len() - 3]);
+    block.push(0x00);
+    block.extend(digest_info);
+    Ok(block)
+}
+
+/// Sign a message with RSASSA-PKCS1-v1_5.
+pub fn pkcs1_v15_sign(
+    message: &[u8],
+    key: &RsaPrivateKey,
+    hash: HashAlgorithm,
+) -> Result<Vec<u8>, &'static str> {
+    let len = key.public_key().size();
+    let block = pkcs1_v15_signature_block(message, hash, len)?;
+    let signature = rsa_decrypt_bigint_crt(&bytes_to_bigint(&block), key);
+    bigint_to_bytes_padded(&signature, len)
+}
+
+// Raise the signature to e and return the resulting block with its
+// leading zeros.
+fn open_signature(signature: &[u8], key: &RsaPublicKey) -> Option<Vec<u8>> {
+    let s = bytes_to_bigint(signature);
+    if signature.len() != key.size() || s >= key.n {
+        return None;
+    }
+    bigint_to_bytes_padded(&rsa_encrypt_bigint(&s, key), key.size()).ok()
+}
+
+/// Strict RSASSA-PKCS1-v1_5 verification. The expected block is rebuilt
+/// from the message and compared in full, so nothing can hide in it.
+pub fn pkcs1_v15_verify(
+    message: &[u8],
+    signature: &[u8],
+    key: &RsaPublicKey,
+    hash: HashAlgorithm,
+) -> bool {
+    let expected = match pkcs1_v15_signature_block(message, hash, key.size()) {
+        Ok(block) => block,
+        Err(_) => return false,
+    };
+    open_signature(signature, key) == Some(expected)
+}
+
+/// A deliberately sloppy RSASSA-PKCS1-v1_5 verifier. It parses the block
+/// from the left, accepts any non-zero amount of padding bytes and reads
+/// the hash right after the DigestInfo header without checking that it is
+/// at the end of the block, so anything may follow the hash.
+pub fn pkcs1_v15_verify_permissive(
+    message: &[u8],
+    signature: &[u8],
+    key: &RsaPublicKey,
+    hash: HashAlgorithm,
+) -> bool {
+    let block = match open_signature(signature, key) {
+        Some(block) => block,
+        None => return false,
+    };
+    if block.len() < 3 || block[0] != 0x00 || block[1] != 0x01 || block[2] != 0xff {
+        return false;
+    }
+    let mut rest = &block[2..];
+    while !rest.is_empty() && rest[0] == 0xff {
+        rest = &rest[1..];
+    }
+    if rest.is_empty() || rest[0] != 0x00 {
+        return false;
+    }
+    rest = &rest[1..];
+
+    let prefix = hash.digest_info_prefix();
+    if !rest.starts_with(prefix) {
+        return false;
+    }
+    rest = &rest[prefix.len()..];
+    let digest = hash.digest(message);
+    rest.len() >= digest.len() && rest[..digest.len()] == digest[..]
+}
+
+// CryptoPals Set 6 Challenge 42
+/// Bleichenbacher's e = 3 signature forgery against verifiers that do not
+/// check the hash is right-justified. The block 00 01 FF 00 DigestInfo is
+/// followed by as much garbage as possible; the smallest integer whose
+/// cube starts with that prefix is a signature the sloppy verifier accepts
+/// when the garbage spans enough bits to absorb the rounding of the cube
+/// root.
+pub fn forge_pkcs1_v15_signature(
+    message: &[u8],
+    key: &RsaPublicKey,
+    hash: HashAlgorithm,
+) -> Result<Vec<u8>, &'static str> {
+    let e = key.e.to_u32().ok_or("Public exponent is too large")?;
+    let len = key.size();
+    let mut prefix = vec![0x00u8, 0x01u8, 0xffu8, 0x00u8];
+    prefix.extend(hash.digest_info(message));
+    if prefix.len() > len {
+        return Err("Modulus is too small for the digest");
+    }
+    let garbage_len = len - prefix.len();
+
+    let mut low_block = prefix.clone();
+    low_block.extend(vec![0x00u8; garbage_len]);
+    let mut high_block = prefix;
+    high_block.extend(vec![0xffu8; garbage_len]);
+    let low = bytes_to_bigint(&low_block);
+    let high = bytes_to_bigint(&high_block);
+
+    // Round the root up so its power lands inside [low, high]
+    let mut signature = kth_root(&low, e);
+    if signature.pow(e) < low {
+        signature += 1u32;
+    }
+    if signature.pow(e) > high || signature >= key.n {
+        return Err("Not enough room for garbage to absorb the root");
+    }
+    bigint_to_bytes_padded(&signature, len)
+}
+
+// A smooth prime of the given size where the signature generates all of
+// Z_p*, the prime factors of p - 1 and the discrete log of the block to
+// the signature mod p - 1.
+fn smooth_prime_with_log(
+    bits: u64,
+    avoid: &[u64],
+    signature: &BigUint,
+    block: &BigUint,
+) -> (BigUint, Vec<u64>, BigUint) {
+    loop {
+        let (p, factors) = generate_smooth_prime(bits, avoid);
+        let p_minus_one = &p - 1u32;
+        let generates = factors
+            .iter()
+            .all(|r| !mod_exp(signature, &(&p_minus_one / *r), &p).is_one());
+        if !generates {
+            continue;
+        }
+        let group = MultiplicativeGroup { p: p.clone() };
+        if let Some(x) = pohlig_hellman(&group, signature, block, &factors, &BabyStepGiantStep) {
+            return (p, factors, x);
+        }
+    }
+}
+
+// CryptoPals Set 8 Challenge 61
+/// Duplicate signature key selection. Build a new key (e', N') with the
+/// same modulus size for which an existing signature s verifies as well.
+/// N' = pq with smooth p - 1 and q - 1 where s generates both groups, so
+/// e' = log_s(m) mod p - 1 and mod q - 1 falls out of Pohlig-Hellman.
+/// Both logs have to agree mod 2, the only factor p - 1 and q - 1 share.
+pub fn pkcs1_v15_duplicate_signature_key(
+    message: &[u8],
+    signature: &[u8],
+    key: &RsaPublicKey,
+    hash: HashAlgorithm,
+) -> Result<RsaPublicKey, &'static str> {
+    let bits = key.n.bits();
+    let block = bytes_to_bigint(&pkcs1_v15_signature_block(message, hash, key.size())?);
+    let s = bytes_to_bigint(signature);
+    if signature.len() != key.size() || s >= key.n {
+        return Err("Signature does not fit the key");
+    }
+
+    let p_bits = bits / 2;
+    loop {
+        let (p, p_factors, x_p) = smooth_prime_with_log(p_bits, &[], &s, &block);
+        let (q, _, x_q) = smooth_prime_with_log(bits - p_bits, &p_factors, &s, &block);
+        let n = &p * &q;
+        if n.bits() != bits || n <= s || n <= block || x_p.is_odd() != x_q.is_odd() {
+            continue;
+        }
+        let moduli = [&p - 1u32, (&q - 1u32) / 2u32];
+        let (e, _) = chinese_remainder(&[x_p, x_q % &moduli[1]], &moduli)?;
+        return Ok(RsaPublicKey { e, n });
+    }
+}
+
+/// RSAES-PKCS1-v1_5 encoding: 00 02 PS 00 M with at least 8 random
+/// non-zero padding bytes, as long as the modulus.
+pub fn pkcs1_v15_encryption_pad(message: &[u8], len: usize) -> Result<Vec<u8>, &'static str> {
+    if message.len() + 11 > len {
+        return Err("Message is too long for the modulus");
+    }
+    let mut rng = thread_rng();
+    let mut block = vec![0x00u8, 0x02u8];
+    for _ in 0..len - message.len() - 3 {
+        block.push(rng.gen_range(1..=255u8));
+    }
+    block.push(0x00);
+    block.extend_from_slice(message);
+    Ok(block)
+}
+
+pub fn pkcs1_v15_encryption_unpad(block: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if block.len() < 11 || block[0] != 0x00 || block[1] != 0x02 {
+        return Err("Block is not PKCS#1 v1.5 conforming");
+    }
+    match block[2..].iter().position(|x| *x == 0x00) {
+        Some(separator) if separator >= 8 => Ok(block[separator + 3..].to_vec()),
+        _ => Err("Block is not PKCS#1 v1.5 conforming"),
+    }
+}
+
+pub fn pkcs1_v15_encrypt(message: &[u8], key: &RsaPublicKey) -> Result<Vec<u8>, &'static str> {
+    let block = pkcs1_v15_encryption_pad(message, key.size())?;
+    let c = rsa_encrypt_bigint(&bytes_to_bigint(&block), key);
+    bigint_to_bytes_padded(&c, key.size())
+}
+
+pub fn pkcs1_v15_decrypt(ciphertext: &[u8], key: &RsaPrivateKey) -> Result<Vec<u8>, &'static str> {
+    let len = key.public_key().size();
+    let c = bytes_to_bigint(ciphertext);
+    if ciphertext.len() != len || c >= key.n {
+        return Err("Ciphertext does not match the modulus");
+    }
+    let block = bigint_to_bytes_padded(&rsa_decrypt_bigint_crt(&c, key), len)?;
+    pkcs1_v15_encryption_unpad(&block)
+}
+
+/// An oracle that decrypts a ciphertext and reveals whether the plaintext
+/// block starts with 00 02, without checking the rest of the padding.
+pub fn pkcs1_v15_padding_oracle(key: RsaPrivateKey) -> impl Fn(&BigUint) -> bool {
+    let len = key.public_key().size();
+    let lower = BigUint::from(2u32) << (8 * (len - 2));
+    let upper = BigUint::from(3u32) << (8 * (len - 2));
+    move |c: &BigUint| {
+        let m = rsa_decrypt_bigint_crt(c, &key);
+        m >= lower && m < upper
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BleichenbacherVariant {
+    /// Only follows a single interval, as in challenge 47. Enough for small
+    /// moduli where the interval rarely splits.
+    Simple,
+    /// Also searches when the solution is spread over several intervals
+    /// (step 2.b of the paper), as in challenge 48.
+    Complete,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BleichenbacherResult {
+    /// The full decrypted block, padding included.
+    pub block: Vec<u8>,
+    pub oracle_calls: usize,
+}
+
+// CryptoPals Set 6 Challenge 47 and 48
+/// Bleichenbacher's 1998 attack against a PKCS#1 v1.5 padding oracle. A
+/// conforming s * m lies in [2B, 3B) with B = 2^(8(k - 2)), so every s the
+/// oracle accepts narrows the set of intervals the plaintext can be in,
+/// until a single value remains.
+pub fn bleichenbacher_attack(
+    ciphertext: &BigUint,
+    key: &RsaPublicKey,
+    oracle: impl Fn(&BigUint) -> bool,
+    variant: BleichenbacherVariant,
+) -> Result<BleichenbacherResult, &'static str> {
+    let n = &key.n;
+    let len = key.size();
+    let big_b = BigUint::one() << (8 * (len - 2));
+    let two_b = &big_b * 2u32;
+    let three_b = &big_b * 3u32;
+    let mut oracle_calls = 0usize;
+    let mut query = |s: &BigUint, c: &BigUint| -> bool {
+        oracle_calls += 1;
+        oracle(&((c * rsa_encrypt_bigint(s, key)) % n))
+    };
+
+    // Step 1: blinding, only needed when the ciphertext itself is not conforming
+    let mut s0 = BigUint::one();
+    while !query(&s0, ciphertext) {
+        s0 = random_range(&BigUint::from(2u32), n);
+    }
+    let c0 = (ciphertext * rsa_encrypt_bigint(&s0, key)) % n;
+
+    let mut intervals: Vec<(BigUint, BigUint)> = vec![(two_b.clone(), &three_b - 1u32)];
+    let mut s = BigUint::zero();
+    for i in 1usize.. {
+        if i == 1 {
+            // Step 2.a: smallest s >= n / 3B giving a conforming plaintext
+            s = n.div_ceil(&three_b);
+            while !query(&s, &c0) {
+                s += 1u32;
+            }
+        } else if intervals.len() > 1 {
+            // Step 2.b: keep searching upwards
+            if variant == BleichenbacherVariant::Simple {
+                return Err("Plaintext spread over several intervals");
+            }
+            s += 1u32;
+            while !query(&s, &c0) {
+                s += 1u32;
+            }
+        } else {
+            // Step 2.c: a single interval [a, b] allows picking r and s so
+            // s roughly doubles each round
+            let (a, b) = &intervals[0];
+            let mut r = ((b * &s - &two_b) * 2u32).div_ceil(n);
+            'search: loop {
+                let low = (&two_b + &r * n).div_ceil(b);
+                let high = (&three_b + &r * n).div_ceil(a);
+                let mut candidate = low;
+                while candidate < high {
+                    if query(&candidate, &c0) {
+                        s = candidate;
+                        break 'search;
+                    }
+                    candidate += 1u32;
+                }
+                r += 1u32;
+            }
+        }
+
+        // Step 3: narrow every interval with the new s
+        let mut narrowed: Vec<(BigUint, BigUint)> = Vec::new();
+        for (a, b) in intervals.iter() {
+            let low_r = if a * &s + 1u32 > three_b {
+                (a * &s + 1u32 - &three_b).div_ceil(n)
+            } else {
+                BigUint::zero()
+            };
+            let high_r = if b * &s >= two_b {
+                (b * &s - &two_b) / n
+            } else {
+                continue;
+            };
+            let mut r = low_r;
+            while r <= high_r {
+                let new_a = (&two_b + &r * n).div_ceil(&s).max(a.clone());
+                let new_b = ((&three_b - 1u32 + &r * n) / &s).min(b.clone());
+                if new_a <= new_b && !narrowed.contains(&(new_a.clone(), new_b.clone())) {
+                    narrowed.push((new_a, new_b));
+                }
+                r += 1u32;
+            }
+        }
+        if narrowed.is_empty() {
+            return Err("No interval left, the oracle is inconsistent");
+        }
+        intervals = narrowed;
+
+        // Step 4: done once a single value is left
+        if intervals.len() == 1 && intervals[0].0 == intervals[0].1 {
+            break;
+        }
+    }
+
+    let m = (&intervals[0].0 * mod_inverse(&s0, n)?) % n;
+    Ok(BleichenbacherResult {
+        block: bigint_to_bytes_padded(&m, len)?,
+        oracle_calls,
+    })
+}
+
+/// MGF1 mask generation function from RFC 8017 built on SHA-1.
+pub fn mgf1_sha1(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut mask: Vec<u8> = Vec::new();
+    let mut counter = 0u32;
+    while mask.len() < len {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&counter.to_be_bytes());
+        mask.extend(sha1(&input));
+        counter += 1;
+    }
+    mask.truncate(len);
+    mask
+}
+
+/// EME-OAEP encoding with SHA-1 and MGF1-SHA-1:
+/// 00 || masked seed || masked (lHash || 00 .. 00 || 01 || message).
+pub fn oaep_encode(message: &[u8], label: &[u8], len: usize) -> Result<Vec<u8>, &'static str> {
+    if len < 2 * SHA1_DIGEST_SIZE + 2 || message.len() > len - 2 * SHA1_DIGEST_SIZE - 2 {
+        return Err("Message is too long for the modulus");
+    }
+    let mut db = sha1(label);
+    db.extend(vec![0x00u8; len - message.len() - 2 * SHA1_DIGEST_SIZE - 2]);
+    db.push(0x01);
+    db.extend_from_slice(message);
+
+    let seed: Vec<u8> = (0..SHA1_DIGEST_SIZE).map(|_| thread_rng().gen()).collect();
+    let masked_db = fixed_xor(&db, &mgf1_sha1(&seed, db.len()));
+    let masked_seed = fixed_xor(&seed, &mgf1_sha1(&masked_db, SHA1_DIGEST_SIZE));
+
+    let mut block = vec![0x00u8];
+    block.extend(masked_seed);
+    block.extend(masked_db);
+    Ok(block)
+}
+
+/// EME-OAEP decoding. Every failure returns the same error, telling
+/// callers apart which check failed is exactly what Manger's attack needs.
+pub fn oaep_decode(block: &[u8], label: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let error = "Decryption error";
+    if block.len() < 2 * SHA1_DIGEST_SIZE + 2 {
+        return Err(error);
+    }
+    let masked_seed = block[1..SHA1_DIGEST_SIZE + 1].to_vec();
+    let masked_db = block[SHA1_DIGEST_SIZE + 1..].to_vec();
+    let seed = fixed_xor(&masked_seed, &mgf1_sha1(&masked_db, SHA1_DIGEST_SIZE));
+    let db = fixed_xor(&masked_db, &mgf1_sha1(&seed, masked_db.len()));
+
+    let label_hash_matches = db[..SHA1_DIGEST_SIZE] == sha1(label)[..];
+    let separator = db[SHA1_DIGEST_SIZE..]
+        .iter()
+        .position(|x| *x != 0x00)
+        .map(|position| position + SHA1_DIGEST_SIZE);
+    match separator {
+        Some(separator) if block[0] == 0x00 && label_hash_matches && db[separator] == 0x01 => {
+            Ok(db[separator + 1..].to_vec())
+        }
+        _ => Err(error),
+    }
+}
+
+pub fn rsa_oaep_encrypt(
+    message: &[u8],
+    label: &[u8],
+    key: &RsaPublicKey,
+) -> Result<Vec<u8>, &'static str> {
+    let block = oaep_encode(message, label, key.size())?;
+    let c = rsa_encrypt_bigint(&bytes_to_bigint(&block), key);
+    bigint_to_bytes_padded(&c, key.size())
+}
+
+pub fn rsa_oaep_decrypt(
+    ciphertext: &[u8],
+    label: &[u8],
+    key: &RsaPrivateKey,
+) -> Result<Vec<u8>, &'static str> {
+    let len = key.public_key().size();
+    let c = bytes_to_bigint(ciphertext);
+    if ciphertext.len() != len || c >= key.n {
+        return Err("Decryption error");
+    }
+    let block = bigint_to_bytes_padded(&rsa_decrypt_bigint_crt(&c, key), len)?;
+    oaep_decode(&block, label)
+}
+
+/// An OAEP implementation that leaks whether the first byte of the
+/// decrypted integer is zero, for example through a distinct error or
+/// through timing. The oracle returns true when it is.
+pub fn manger_oracle(key: RsaPrivateKey) -> impl Fn(&BigUint) -> bool {
+    let len = key.public_key().size();
+    let big_b = BigUint::one() << (8 * (len - 1));
+    move |c: &BigUint| rsa_decrypt_bigint_crt(c, &key) < big_b
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MangerResult {
+    /// The full decrypted block, OAEP encoding included.
+    pub block: Vec<u8>,
+    pub oracle_calls: usize,
+}
+
+/// Manger's 2001 attack against RSA-OAEP. With B = 2^(8(k - 1)) the oracle
+/// answers whether f * m mod n < B. Steps one and two find multipliers that
+/// place f * m just past n, after which every query halves the interval the
+/// plaintext lies in, needing roughly log2(n) queries in total.
+pub fn manger_attack(
+    ciphertext: &BigUint,
+    key: &RsaPublicKey,
+    oracle: impl Fn(&BigUint) -> bool,
+) -> Result<MangerResult, &'static str> {
+    let n = &key.n;
+    let len = key.size();
+    let big_b = BigUint::one() << (8 * (len - 1));
+    if &big_b * 2u32 >= *n {
+        return Err("Manger's attack needs 2B < n");
+    }
+    let mut oracle_calls = 0usize;
+    let mut below_b = |f: &BigUint| -> bool {
+        oracle_calls += 1;
+        oracle(&((ciphertext * rsa_encrypt_bigint(f, key)) % n))
+    };
+
+    // Step 1: double f1 until f1 * m >= B, so f1 / 2 * m lies in [B / 2, B)
+    let mut f1 = BigUint::from(2u32);
+    while below_b(&f1) {
+        f1 <<= 1;
+    }
+    let half_f1 = &f1 >> 1;
+
+    // Step 2: find f2 with f2 * m in [n, n + B)
+    let mut f2 = ((n + &big_b) / &big_b) * &half_f1;
+    while !below_b(&f2) {
+        f2 += &half_f1;
+    }
+
+    // Step 3: binary search on m, each query roughly halving [m_min, m_max]
+    let mut m_min = n.div_ceil(&f2);
+    let mut m_max = (n + &big_b) / &f2;
+    while m_min < m_max {
+        let f_tmp = (&big_b * 2u32) / (&m_max - &m_min);
+        let i = (&f_tmp * &m_min) / n;
+        let f3 = (&i * n).div_ceil(&m_min);
+        if below_b(&f3) {
+            m_max = (&i * n + &big_b) / &f3;
+        } else {
+            m_min = (&i * n + &big_b).div_ceil(&f3);
+        }
+    }
+
+    if rsa_encrypt_bigint(&m_min, key) != *ciphertext {
+        return Err("Recovered plaintext does not match, the oracle is inconsistent");
+    }
+    Ok(MangerResult {
+        block: bigint_to_bytes_padded(&m_min, len)?,
+        oracle_calls,
+    })
+}
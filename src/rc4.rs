@@ -0,0 +1,170 @@
+use rand::{thread_rng, Rng};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The cookie of challenge 56, base64 encoded.
+pub const RC4_COOKIE: &str = "QkUgU1VSRSBUTyBEUklOSyBZT1VSIE9WQUxUSU5F";
+
+// The keystream byte at position 16 leans towards 240 and the one at
+// position 32 towards 224 (AlFardan et al.). The weights are the log of how
+// much likelier the biased value is, about 2^-4.5 and 2^-5.8.
+const BIASES: [(usize, u8, f64); 2] = [(15, 240, 0.043), (31, 224, 0.0175)];
+const PROGRESS_INTERVAL: usize = 1 << 16;
+
+pub struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    pub fn new(key: &[u8]) -> Result<Rc4, &'static str> {
+        if key.is_empty() {
+            return Err("RC4 key must not be empty");
+        }
+        let mut state = [0u8; 256];
+        for (i, s) in state.iter_mut().enumerate() {
+            *s = i as u8;
+        }
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+            state.swap(i, j as usize);
+        }
+        Ok(Rc4 { state, i: 0, j: 0 })
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.state[self.i as usize]);
+        self.state.swap(self.i as usize, self.j as usize);
+        let index = self.state[self.i as usize].wrapping_add(self.state[self.j as usize]);
+        self.state[index as usize]
+    }
+
+    pub fn apply(&mut self, data: &[u8]) -> Vec<u8> {
+        data.iter().map(|b| b ^ self.next_byte()).collect()
+    }
+}
+
+/// RC4 is its own inverse, this both encrypts and decrypts.
+pub fn rc4(data: &[u8], key: &[u8]) -> Result<Vec<u8>, &'static str> {
+    Ok(Rc4::new(key)?.apply(data))
+}
+
+/// Encrypts `request || cookie` under a fresh 128 bit key on every call, like
+/// a browser that can be made to send requests to an attacker chosen path.
+pub fn rc4_cookie_oracle(cookie: &[u8]) -> impl Fn(&[u8]) -> Vec<u8> + Sync {
+    let cookie = cookie.to_vec();
+    move |request: &[u8]| {
+        let key: [u8; 16] = thread_rng().gen();
+        let mut plaintext = request.to_vec();
+        plaintext.extend_from_slice(&cookie);
+        rc4(&plaintext, &key).unwrap()
+    }
+}
+
+// Count the ciphertext bytes at the biased keystream positions over
+// `samples` encryptions of `request`, split across `threads` workers.
+fn collect_counts(
+    oracle: &(impl Fn(&[u8]) -> Vec<u8> + Sync),
+    request: &[u8],
+    samples: usize,
+    threads: usize,
+    done: &AtomicUsize,
+    report: &(impl Fn(usize) + Sync),
+) -> Vec<[u64; 256]> {
+    let workers: Vec<Vec<[u64; 256]>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|worker| {
+                let share = samples / threads + usize::from(worker < samples % threads);
+                scope.spawn(move || {
+                    let mut counts = vec![[0u64; 256]; BIASES.len()];
+                    for sample in 1..=share {
+                        let ciphertext = oracle(request);
+                        for (count, (position, _, _)) in counts.iter_mut().zip(BIASES) {
+                            if let Some(byte) = ciphertext.get(position) {
+                                count[*byte as usize] += 1;
+                            }
+                        }
+                        if sample % PROGRESS_INTERVAL == 0 || sample == share {
+                            let step = (sample - 1) % PROGRESS_INTERVAL + 1;
+                            report(done.fetch_add(step, Ordering::Relaxed) + step);
+                        }
+                    }
+                    counts
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut counts = vec![[0u64; 256]; BIASES.len()];
+    for worker in workers {
+        for (total, count) in counts.iter_mut().zip(worker) {
+            for (t, c) in total.iter_mut().zip(count) {
+                *t += c;
+            }
+        }
+    }
+    counts
+}
+
+/// Recover the first `cookie_len` bytes following the request from
+/// `samples` encryptions per request length. Padding the request moves each
+/// cookie byte under keystream bytes 16 and 32, where the most frequent
+/// ciphertext byte xor the biased value gives away the plaintext. Sampling
+/// runs on `threads` threads and `progress` is called with the number of
+/// encryptions done so far and the total. Returns the recovered bytes.
+///
+/// The bias at byte 32 is weak, the challenge needs around 2^24 samples.
+pub fn rc4_bias_attack(
+    oracle: impl Fn(&[u8]) -> Vec<u8> + Sync,
+    cookie_len: usize,
+    samples: usize,
+    threads: usize,
+    progress: impl Fn(usize, usize) + Sync,
+) -> Result<Vec<u8>, &'static str> {
+    let (last_position, _, _) = BIASES[BIASES.len() - 1];
+    if cookie_len > last_position + 1 {
+        return Err("Cookie does not fit under the biased keystream bytes");
+    }
+    if threads == 0 {
+        return Err("Need at least one thread");
+    }
+
+    // Request lengths that put some cookie byte under a biased position.
+    let request_lens: Vec<usize> = (0..=last_position)
+        .filter(|len| {
+            BIASES
+                .iter()
+                .any(|(position, _, _)| position >= len && position - len < cookie_len)
+        })
+        .collect();
+    let total = request_lens.len() * samples;
+    let done = AtomicUsize::new(0);
+    let report = |done: usize| progress(done, total);
+
+    let mut scores = vec![[0f64; 256]; cookie_len];
+    for len in request_lens {
+        let request = vec![b'A'; len];
+        let counts = collect_counts(&oracle, &request, samples, threads, &done, &report);
+        for (count, (position, bias, weight)) in counts.iter().zip(BIASES) {
+            if position < len || position - len >= cookie_len {
+                continue;
+            }
+            for (byte, score) in scores[position - len].iter_mut().enumerate() {
+                *score += weight * count[byte ^ bias as usize] as f64;
+            }
+        }
+    }
+
+    Ok(scores
+        .iter()
+        .map(|byte_scores| {
+            (0..=255u8)
+                .max_by(|a, b| byte_scores[*a as usize].total_cmp(&byte_scores[*b as usize]))
+                .unwrap()
+        })
+        .collect())
+}
@@ -0,0 +1,366 @@
+use crate::b64::{b64_decode, b64_encode};
+use crate::bigint::{
+    bigint_to_bytes, bigint_to_bytes_padded, bytes_to_bigint, chinese_remainder, exact_kth_root,
+    gcd, generate_prime, mod_exp, mod_inverse, modulo, random_range, BigInt, BigUint,
+};
+use crate::lattice::{coppersmith_small_roots, poly_pow};
+use crate::utils::OnceOracle;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+pub const RSA_DEFAULT_EXPONENT: u32 = 65537;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RsaPublicKey {
+    pub e: BigUint,
+    pub n: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RsaPrivateKey {
+    pub n: BigUint,
+    pub e: BigUint,
+    pub d: BigUint,
+    pub p: BigUint,
+    pub q: BigUint,
+    pub dp: BigUint,
+    pub dq: BigUint,
+    pub qinv: BigUint,
+}
+
+impl RsaPrivateKey {
+    pub fn public_key(&self) -> RsaPublicKey {
+        RsaPublicKey {
+            e: self.e.clone(),
+            n: self.n.clone(),
+        }
+    }
+}
+
+impl RsaPublicKey {
+    /// Size of the modulus in bytes, which is also the size of every ciphertext.
+    pub fn size(&self) -> usize {
+        self.n.bits().div_ceil(8) as usize
+    }
+}
+
+// CryptoPals Set 5 Challenge 39
+/// Generate an RSA key pair with a modulus of the given amount of bits and
+/// public exponent e. Primes are regenerated until e is invertible mod
+/// (p - 1)(q - 1). Panics for moduli below 4 bits.
+pub fn generate_rsa_keys(bits: u64, e: &BigUint) -> (RsaPublicKey, RsaPrivateKey) {
+    loop {
+        let p = generate_prime(bits / 2).expect("RSA modulus needs at least 4 bits");
+        let q = generate_prime(bits - bits / 2).expect("RSA modulus needs at least 4 bits");
+        if p == q {
+            continue;
+        }
+        let p_minus_one = &p - 1u32;
+        let q_minus_one = &q - 1u32;
+        if !gcd(e, &p_minus_one).is_one() || !gcd(e, &q_minus_one).is_one() {
+            continue;
+        }
+        let et = &p_minus_one * &q_minus_one;
+        let d = mod_inverse(e, &et).unwrap();
+        let private = RsaPrivateKey {
+            n: &p * &q,
+            e: e.clone(),
+            dp: &d % &p_minus_one,
+            dq: &d % &q_minus_one,
+            qinv: mod_inverse(&q, &p).unwrap(),
+            d,
+            p,
+            q,
+        };
+        return (private.public_key(), private);
+    }
+}
+
+/// Textbook RSA encryption of an integer, m^e mod n.
+pub fn rsa_encrypt_bigint(m: &BigUint, key: &RsaPublicKey) -> BigUint {
+    mod_exp(m, &key.e, &key.n)
+}
+
+/// Textbook RSA decryption of an integer, c^d mod n.
+pub fn rsa_decrypt_bigint(c: &BigUint, key: &RsaPrivateKey) -> BigUint {
+    mod_exp(c, &key.d, &key.n)
+}
+
+/// RSA decryption using the Chinese Remainder Theorem. Two half size
+/// exponentiations are recombined with Garner's formula which is roughly
+/// four times faster than a single exponentiation mod n.
+pub fn rsa_decrypt_bigint_crt(c: &BigUint, key: &RsaPrivateKey) -> BigUint {
+    let m1 = mod_exp(c, &key.dp, &key.p);
+    let m2 = mod_exp(c, &key.dq, &key.q);
+    let h = (&key.qinv * ((&m1 + &key.p) - (&m2 % &key.p))) % &key.p;
+    m2 + h * &key.q
+}
+
+/// Encrypt a message with textbook RSA. The ciphertext is always as
+/// long as the modulus.
+pub fn rsa_encrypt(plaintext: &[u8], key: &RsaPublicKey) -> Result<Vec<u8>, &'static str> {
+    let m = bytes_to_bigint(plaintext);
+    if m >= key.n {
+        return Err("Message is too large for the modulus");
+    }
+    bigint_to_bytes_padded(&rsa_encrypt_bigint(&m, key), key.size())
+}
+
+/// Decrypt a textbook RSA ciphertext. Leading zero bytes of the
+/// plaintext can not be recovered.
+pub fn rsa_decrypt(ciphertext: &[u8], key: &RsaPrivateKey) -> Result<Vec<u8>, &'static str> {
+    let c = bytes_to_bigint(ciphertext);
+    if c >= key.n {
+        return Err("Ciphertext is too large for the modulus");
+    }
+    Ok(bigint_to_bytes(&rsa_decrypt_bigint_crt(&c, key)))
+}
+
+// Keys are serialised as a sequence of integers, each prefixed with its
+// length as a 4 byte big-endian number, and then base64 encoded.
+fn serialise_integers(integers: &[&BigUint]) -> String {
+    let mut bytes: Vec<u8> = Vec::new();
+    for integer in integers.iter() {
+        let integer_bytes = bigint_to_bytes(integer);
+        bytes.extend_from_slice(&(integer_bytes.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&integer_bytes);
+    }
+    b64_encode(&bytes)
+}
+
+fn deserialise_integers(encoded: &str, count: usize) -> Result<Vec<BigUint>, &'static str> {
+    let bytes = b64_decode(&encoded.to_string()).map_err(|_| "Key is not valid base64")?;
+    let mut integers: Vec<BigUint> = Vec::new();
+    let mut rest = bytes.as_slice();
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return Err("Truncated key");
+        }
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        rest = &rest[4..];
+        if rest.len() < len {
+            return Err("Truncated key");
+        }
+        integers.push(bytes_to_bigint(&rest[..len]));
+        rest = &rest[len..];
+    }
+    if integers.len() != count {
+        return Err("Unexpected amount of integers in key");
+    }
+    Ok(integers)
+}
+
+pub fn rsa_public_key_to_b64(key: &RsaPublicKey) -> String {
+    serialise_integers(&[&key.e, &key.n])
+}
+
+pub fn rsa_public_key_from_b64(encoded: &str) -> Result<RsaPublicKey, &'static str> {
+    let mut integers = deserialise_integers(encoded, 2)?;
+    let n = integers.pop().unwrap();
+    let e = integers.pop().unwrap();
+    Ok(RsaPublicKey { e, n })
+}
+
+pub fn rsa_private_key_to_b64(key: &RsaPrivateKey) -> String {
+    serialise_integers(&[&key.e, &key.d, &key.p, &key.q])
+}
+
+/// Load a private key, recomputing n and the CRT parameters from p and q.
+pub fn rsa_private_key_from_b64(encoded: &str) -> Result<RsaPrivateKey, &'static str> {
+    let integers = deserialise_integers(encoded, 4)?;
+    let (e, d, p, q) = (&integers[0], &integers[1], &integers[2], &integers[3]);
+    Ok(RsaPrivateKey {
+        n: p * q,
+        e: e.clone(),
+        d: d.clone(),
+        p: p.clone(),
+        q: q.clone(),
+        dp: d % (p - 1u32),
+        dq: d % (q - 1u32),
+        qinv: mod_inverse(q, p)?,
+    })
+}
+
+// CryptoPals Set 5 Challenge 40
+/// Hastad's broadcast attack. When the same message is encrypted to at least
+/// e recipients with the same small public exponent e, combining the
+/// ciphertexts with the CRT gives m^e modulo the product of the moduli.
+/// Since m is smaller than every modulus m^e does not wrap around and an
+/// exact integer e-th root recovers the message.
+pub fn hastad_broadcast_attack(
+    ciphertexts: &[(BigUint, RsaPublicKey)],
+) -> Result<BigUint, &'static str> {
+    let e = common_small_exponent(ciphertexts.iter().map(|(_, key)| key))?;
+    if ciphertexts.len() < e as usize {
+        return Err("Need at least e ciphertexts");
+    }
+    let residues: Vec<BigUint> = ciphertexts.iter().map(|(c, _)| c.clone()).collect();
+    let moduli: Vec<BigUint> = ciphertexts.iter().map(|(_, key)| key.n.clone()).collect();
+    let (m_to_e, _) = chinese_remainder(&residues, &moduli)?;
+    exact_kth_root(&m_to_e, e).ok_or("Combined ciphertext is not a perfect power")
+}
+
+fn common_small_exponent<'a>(
+    mut keys: impl Iterator<Item = &'a RsaPublicKey>,
+) -> Result<u32, &'static str> {
+    let e = keys.next().ok_or("Need at least one ciphertext")?.e.clone();
+    if keys.any(|key| key.e != e) {
+        return Err("Recipients have to share the public exponent");
+    }
+    e.to_u32().ok_or("Public exponent is too large")
+}
+
+/// Known per recipient linear padding, the recipient receives the
+/// encryption of a * m + b mod n instead of m.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearPadding {
+    pub a: BigUint,
+    pub b: BigUint,
+}
+
+#[derive(Clone, Debug)]
+pub struct PaddedBroadcast {
+    pub ciphertext: BigUint,
+    pub key: RsaPublicKey,
+    pub padding: LinearPadding,
+}
+
+pub fn rsa_encrypt_linear_padded(
+    m: &BigUint,
+    padding: &LinearPadding,
+    key: &RsaPublicKey,
+) -> BigUint {
+    let padded = (&padding.a * m + &padding.b) % &key.n;
+    rsa_encrypt_bigint(&padded, key)
+}
+
+const MAX_COPPERSMITH_DEPTH: usize = 4;
+
+/// Generalised Hastad attack for recipients that apply a known linear
+/// padding to the message. Every recipient gives a polynomial
+/// (a_i x + b_i)^e - c_i with the message as root modulo n_i; combining the
+/// coefficients with the CRT gives a single polynomial with the message as
+/// root modulo the product N. Coppersmith's method then finds that root as
+/// long as the message is small enough compared to N, which is why k > e
+/// recipients allow recovering longer messages. message_bits is an upper
+/// bound on the size of the message.
+pub fn hastad_padded_broadcast_attack(
+    broadcasts: &[PaddedBroadcast],
+    message_bits: u64,
+) -> Result<BigUint, &'static str> {
+    let e = common_small_exponent(broadcasts.iter().map(|broadcast| &broadcast.key))? as usize;
+    let moduli: Vec<BigUint> = broadcasts.iter().map(|b| b.key.n.clone()).collect();
+
+    let polys: Vec<Vec<BigInt>> = broadcasts
+        .iter()
+        .map(|broadcast| {
+            let linear = vec![
+                BigInt::from(broadcast.padding.b.clone()),
+                BigInt::from(broadcast.padding.a.clone()),
+            ];
+            let mut poly = poly_pow(&linear, e);
+            poly[0] -= BigInt::from(broadcast.ciphertext.clone());
+            poly
+        })
+        .collect();
+
+    let mut combined: Vec<BigInt> = Vec::new();
+    for degree in 0..=e {
+        let residues: Vec<BigUint> = polys
+            .iter()
+            .zip(moduli.iter())
+            .map(|(poly, n)| modulo(&poly[degree], n))
+            .collect();
+        let (coefficient, _) = chinese_remainder(&residues, &moduli)?;
+        combined.push(BigInt::from(coefficient));
+    }
+    let product: BigUint = moduli.iter().product();
+
+    let bound = BigUint::one() << message_bits;
+    for depth in 1..=MAX_COPPERSMITH_DEPTH {
+        for root in coppersmith_small_roots(&combined, &product, &bound, depth)? {
+            let consistent = broadcasts.iter().all(|broadcast| {
+                rsa_encrypt_linear_padded(&root, &broadcast.padding, &broadcast.key)
+                    == broadcast.ciphertext
+            });
+            if consistent {
+                return Ok(root);
+            }
+        }
+    }
+    Err("Message is too large to recover from these recipients")
+}
+
+pub type RsaDecryptionOracle = OnceOracle<BigUint, Result<BigUint, &'static str>>;
+
+/// A decryption service that refuses to decrypt the same ciphertext twice.
+/// Ciphertexts that are not reduced modulo n are rejected so every
+/// plaintext has exactly one accepted ciphertext.
+pub fn rsa_decryption_oracle(key: RsaPrivateKey) -> RsaDecryptionOracle {
+    OnceOracle::new(move |c: &BigUint| {
+        if *c >= key.n {
+            return Err("Ciphertext is too large for the modulus");
+        }
+        Ok(rsa_decrypt_bigint_crt(c, &key))
+    })
+}
+
+// CryptoPals Set 6 Challenge 41
+/// Recover the plaintext of a ciphertext the oracle has already decrypted.
+/// Textbook RSA is multiplicative so submitting s^e * c decrypts to s * m,
+/// a ciphertext the oracle has never seen, and dividing by s gives m.
+pub fn unpadded_message_recovery(
+    ciphertext: &BigUint,
+    key: &RsaPublicKey,
+    oracle: &mut RsaDecryptionOracle,
+) -> Result<BigUint, &'static str> {
+    let two = BigUint::from(2u32);
+    let (s, s_inverse) = loop {
+        let s = random_range(&two, &key.n);
+        if let Ok(s_inverse) = mod_inverse(&s, &key.n) {
+            break (s, s_inverse);
+        }
+    };
+    let blinded = (rsa_encrypt_bigint(&s, key) * ciphertext) % &key.n;
+    let blinded_plaintext = oracle.query(&blinded)??;
+    Ok((blinded_plaintext * s_inverse) % &key.n)
+}
+
+/// An oracle that decrypts a ciphertext and only reveals whether the
+/// plaintext is even.
+pub fn rsa_parity_oracle(key: RsaPrivateKey) -> impl Fn(&BigUint) -> bool {
+    move |c: &BigUint| rsa_decrypt_bigint_crt(c, &key).is_even()
+}
+
+// CryptoPals Set 6 Challenge 46
+/// Decrypt a ciphertext using only a parity oracle. Multiplying the
+/// ciphertext by 2^e doubles the plaintext, and 2m mod n is even exactly when
+/// the doubling did not wrap around n, so every query reveals one more bit
+/// of floor(2^i * m / n). The plaintext lies in [a * n / 2^i, (a + 1) * n / 2^i)
+/// and the bounds are tracked as the exact integer a instead of rounded
+/// values, so the final answer, the ceiling of the lower bound, is exact.
+/// The optional progress callback receives the current upper bound after
+/// every query.
+pub fn rsa_parity_attack(
+    ciphertext: &BigUint,
+    key: &RsaPublicKey,
+    oracle: impl Fn(&BigUint) -> bool,
+    mut progress: Option<&mut dyn FnMut(&BigUint)>,
+) -> BigUint {
+    let multiplier = rsa_encrypt_bigint(&BigUint::from(2u32), key);
+    let mut c = ciphertext.clone();
+    let mut a = BigUint::zero();
+    let mut denominator = BigUint::one();
+    for _ in 0..key.n.bits() {
+        c = (c * &multiplier) % &key.n;
+        a <<= 1;
+        denominator <<= 1;
+        if !oracle(&c) {
+            a += 1u32;
+        }
+        if let Some(callback) = progress.as_mut() {
+            callback(&((&a + 1u32) * &key.n / &denominator));
+        }
+    }
+    (a * &key.n).div_ceil(&denominator)
+}
@@ -0,0 +1,61 @@
+pub const SHA1_DIGEST_SIZE: usize = 20usize;
+pub const SHA1_BLOCK_SIZE: usize = 64usize;
+
+const SHA1_INITIAL_STATE: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+/// Merkle-Damgard padding for a message of the given length: a one bit,
+/// zeros up to 56 mod 64 bytes and the message length in bits as a 64 bit
+/// big-endian number.
+pub fn sha1_padding(message_len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80u8];
+    let zeros = (SHA1_BLOCK_SIZE + 55 - message_len % SHA1_BLOCK_SIZE) % SHA1_BLOCK_SIZE;
+    padding.extend(vec![0u8; zeros]);
+    padding.extend_from_slice(&((message_len as u64) * 8).to_be_bytes());
+    padding
+}
+
+fn sha1_compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5A827999),
+            20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+            _ => (b ^ c ^ d, 0xCA62C1D6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (value, new) in state.iter_mut().zip([a, b, c, d, e]) {
+        *value = value.wrapping_add(new);
+    }
+}
+
+/// Calculate the SHA-1 digest of a message.
+pub fn sha1(message: &[u8]) -> Vec<u8> {
+    let mut state = SHA1_INITIAL_STATE;
+    let mut padded = message.to_vec();
+    padded.extend(sha1_padding(message.len()));
+    for block in padded.chunks(SHA1_BLOCK_SIZE) {
+        sha1_compress(&mut state, block);
+    }
+    state.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
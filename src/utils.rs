@@ -1,6 +1,7 @@
 // TODO Add documentation to this file
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 
 pub struct DecipheredMessage {
     pub bytes: Vec<u8>,
@@ -121,3 +122,32 @@ pub fn pkcs_7_unpad(data: &Vec<u8>) -> Vec<u8> {
     unpadded_data = data[0..data.len() - pad_len as usize].to_vec();
     unpadded_data
 }
+
+/// An oracle that answers every distinct input only once. Repeated inputs
+/// are rejected, like a server that remembers every message it has
+/// already processed.
+pub struct OnceOracle<T, R> {
+    seen: HashSet<T>,
+    answer: Box<dyn FnMut(&T) -> R>,
+}
+
+impl<T: Eq + Hash + Clone, R> OnceOracle<T, R> {
+    pub fn new(answer: impl FnMut(&T) -> R + 'static) -> OnceOracle<T, R> {
+        OnceOracle {
+            seen: HashSet::new(),
+            answer: Box::new(answer),
+        }
+    }
+
+    pub fn query(&mut self, input: &T) -> Result<R, &'static str> {
+        if !self.seen.insert(input.clone()) {
+            return Err("Input has already been processed");
+        }
+        Ok((self.answer)(input))
+    }
+
+    /// Amount of distinct inputs answered so far.
+    pub fn queries(&self) -> usize {
+        self.seen.len()
+    }
+}
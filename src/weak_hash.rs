@@ -0,0 +1,555 @@
+use crate::aes::{encrypt_aes_ecb, AES_BLOCK_SIZE};
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::fs;
+
+pub const WEAK_HASH_BLOCK_SIZE: usize = AES_BLOCK_SIZE;
+
+/// A deliberately weak Merkle-Damgard hash for the Set 7 hash attacks. The
+/// state is only a few bytes and the compression function encrypts the
+/// message block with AES-128 keyed by the zero padded state, truncating the
+/// result back to the state size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeakHash {
+    pub initial_state: Vec<u8>,
+}
+
+impl WeakHash {
+    pub fn new(initial_state: &[u8]) -> WeakHash {
+        assert!(
+            !initial_state.is_empty() && initial_state.len() <= AES_BLOCK_SIZE,
+            "State has to be between 1 and 16 bytes"
+        );
+        WeakHash {
+            initial_state: initial_state.to_vec(),
+        }
+    }
+
+    pub fn state_size(&self) -> usize {
+        self.initial_state.len()
+    }
+
+    pub fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8> {
+        let mut key = state.to_vec();
+        key.resize(AES_BLOCK_SIZE, 0u8);
+        let mut encrypted = encrypt_aes_ecb(&block.to_vec(), &key, None);
+        encrypted.truncate(self.state_size());
+        encrypted
+    }
+
+    /// Run the compression function over whole blocks without any padding.
+    pub fn process(&self, state: &[u8], blocks: &[u8]) -> Vec<u8> {
+        blocks
+            .chunks(WEAK_HASH_BLOCK_SIZE)
+            .fold(state.to_vec(), |state, block| self.compress(&state, block))
+    }
+
+    pub fn hash(&self, message: &[u8]) -> Vec<u8> {
+        let mut padded = message.to_vec();
+        padded.extend(weak_hash_padding(message.len()));
+        self.process(&self.initial_state, &padded)
+    }
+}
+
+/// Merkle-Damgard strengthening for 16 byte blocks: a one bit, zeros up to
+/// 8 mod 16 bytes and the message length in bits as a 64 bit big-endian
+/// number.
+pub fn weak_hash_padding(message_len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80u8];
+    let zeros =
+        (WEAK_HASH_BLOCK_SIZE + 7 - message_len % WEAK_HASH_BLOCK_SIZE) % WEAK_HASH_BLOCK_SIZE;
+    padding.extend(vec![0u8; zeros]);
+    padding.extend_from_slice(&((message_len as u64) * 8).to_be_bytes());
+    padding
+}
+
+/// Two different blocks that take `state` to the same `next_state`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockCollision {
+    pub state: Vec<u8>,
+    pub blocks: [Vec<u8>; 2],
+    pub next_state: Vec<u8>,
+}
+
+/// Birthday search for a single block collision from the given state.
+/// Returns the collision and the number of compression function calls.
+pub fn find_block_collision(hash: &WeakHash, state: &[u8]) -> (BlockCollision, usize) {
+    let mut rng = thread_rng();
+    let mut seen: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    let mut compressions = 0usize;
+    loop {
+        let block: [u8; WEAK_HASH_BLOCK_SIZE] = rng.gen();
+        let next_state = hash.compress(state, &block);
+        compressions += 1;
+        match seen.get(&next_state) {
+            Some(other) if other[..] != block[..] => {
+                let collision = BlockCollision {
+                    state: state.to_vec(),
+                    blocks: [other.clone(), block.to_vec()],
+                    next_state,
+                };
+                return (collision, compressions);
+            }
+            _ => {
+                seen.insert(next_state, block.to_vec());
+            }
+        }
+    }
+}
+
+/// Joux's multicollision: n successive block collisions, each found from
+/// the state the previous one ends in. Choosing either block at every step
+/// gives 2^n messages of n blocks that all hash the same, for the price of
+/// n birthday searches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Multicollision {
+    pub state: Vec<u8>,
+    pub collisions: Vec<BlockCollision>,
+    pub compressions: usize,
+}
+
+impl Multicollision {
+    pub fn new(state: &[u8]) -> Multicollision {
+        Multicollision {
+            state: state.to_vec(),
+            collisions: Vec::new(),
+            compressions: 0,
+        }
+    }
+
+    pub fn final_state(&self) -> &[u8] {
+        match self.collisions.last() {
+            Some(collision) => &collision.next_state,
+            None => &self.state,
+        }
+    }
+
+    /// Double the number of colliding messages with one more collision.
+    pub fn extend(&mut self, hash: &WeakHash) {
+        let (collision, compressions) = find_block_collision(hash, self.final_state());
+        self.collisions.push(collision);
+        self.compressions += compressions;
+    }
+
+    pub fn message_count(&self) -> usize {
+        1usize << self.collisions.len()
+    }
+
+    /// The colliding message picking the second block wherever bit i of
+    /// `index` is set.
+    pub fn message(&self, index: usize) -> Vec<u8> {
+        self.collisions
+            .iter()
+            .enumerate()
+            .flat_map(|(i, collision)| collision.blocks[(index >> i) & 1].clone())
+            .collect()
+    }
+}
+
+/// Generate 2^n messages that collide under `hash`.
+pub fn joux_multicollision(hash: &WeakHash, n: usize) -> Multicollision {
+    let mut multicollision = Multicollision::new(&hash.initial_state);
+    for _ in 0..n {
+        multicollision.extend(hash);
+    }
+    multicollision
+}
+
+/// The concatenation f(x) || g(x), which naively should be as strong as
+/// f and g combined.
+pub fn cascade_hash(f: &WeakHash, g: &WeakHash, message: &[u8]) -> Vec<u8> {
+    let mut digest = f.hash(message);
+    digest.extend(g.hash(message));
+    digest
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CascadeCollision {
+    pub messages: [Vec<u8>; 2],
+    /// Calls to f's compression function, all spent on birthday searches.
+    pub f_compressions: usize,
+    /// Calls to g's compression function spent hashing the multicollision.
+    pub g_compressions: usize,
+}
+
+/// Collide f(x) || g(x) with a Joux multicollision in the cheaper f. With
+/// 2^(b/2) messages colliding in f, where g has a b bit state, one pair
+/// likely collides in g as well; if not, one more f collision doubles the
+/// pool. The cost is about b/2 birthday searches in f plus 2^(b/2) in g,
+/// instead of the 2^((b_f + b_g) / 2) a generic birthday attack needs.
+pub fn find_cascade_collision(f: &WeakHash, g: &WeakHash) -> CascadeCollision {
+    let mut multicollision = Multicollision::new(&f.initial_state);
+    // g states of every colliding message, indexed like Multicollision::message
+    let mut g_states: Vec<Vec<u8>> = vec![g.initial_state.clone()];
+    let mut g_compressions = 0usize;
+    let initial_collisions = g.state_size() * 8 / 2;
+
+    loop {
+        multicollision.extend(f);
+        let collision = multicollision.collisions.last().unwrap();
+        let mut next_states = vec![Vec::new(); 2 * g_states.len()];
+        for (index, state) in g_states.iter().enumerate() {
+            next_states[index] = g.compress(state, &collision.blocks[0]);
+            next_states[index + g_states.len()] = g.compress(state, &collision.blocks[1]);
+        }
+        g_compressions += next_states.len();
+        g_states = next_states;
+        if multicollision.collisions.len() < initial_collisions {
+            continue;
+        }
+
+        // The messages have the same length so their padding is the same
+        // too, colliding states mean colliding hashes
+        let mut seen: HashMap<&Vec<u8>, usize> = HashMap::new();
+        for (index, state) in g_states.iter().enumerate() {
+            if let Some(other) = seen.insert(state, index) {
+                return CascadeCollision {
+                    messages: [multicollision.message(other), multicollision.message(index)],
+                    f_compressions: multicollision.compressions,
+                    g_compressions,
+                };
+            }
+        }
+    }
+}
+
+/// One step of an expandable message: a single block and a message of
+/// 2^j + 1 blocks that both take `state` to `next_state`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpandablePiece {
+    pub state: Vec<u8>,
+    pub short: Vec<u8>,
+    pub long: Vec<u8>,
+    pub next_state: Vec<u8>,
+}
+
+// Birthday search for blocks x and y with compress(a, x) = compress(b, y).
+// Returns x, y, the common state and the number of compressions.
+fn find_cross_collision(hash: &WeakHash, a: &[u8], b: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>, usize) {
+    let mut rng = thread_rng();
+    let mut a_states: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    let mut b_states: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    let mut compressions = 0usize;
+    loop {
+        let x: [u8; WEAK_HASH_BLOCK_SIZE] = rng.gen();
+        let y: [u8; WEAK_HASH_BLOCK_SIZE] = rng.gen();
+        let a_state = hash.compress(a, &x);
+        let b_state = hash.compress(b, &y);
+        compressions += 2;
+        a_states.insert(a_state.clone(), x.to_vec());
+        b_states.insert(b_state.clone(), y.to_vec());
+
+        if let Some(y) = b_states.get(&a_state) {
+            return (x.to_vec(), y.clone(), a_state, compressions);
+        }
+        if let Some(x) = a_states.get(&b_state) {
+            return (x.clone(), y.to_vec(), b_state, compressions);
+        }
+    }
+}
+
+// Collide one block from `state` with 2^j zero blocks followed by one block.
+fn find_expandable_piece(hash: &WeakHash, state: &[u8], j: usize) -> (ExpandablePiece, usize) {
+    let mut long = vec![0u8; WEAK_HASH_BLOCK_SIZE << j];
+    let dummy_state = hash.process(state, &long);
+    let (short, last, next_state, compressions) = find_cross_collision(hash, state, &dummy_state);
+    long.extend(last);
+    let piece = ExpandablePiece {
+        state: state.to_vec(),
+        short,
+        long,
+        next_state,
+    };
+    (piece, compressions + (1usize << j))
+}
+
+/// Kelsey and Schneier's expandable message: k pieces where piece i offers
+/// a choice between 1 and 2^(k - 1 - i) + 1 blocks, so the pieces combine
+/// into a colliding message of any length from k to k + 2^k - 1 blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpandableMessage {
+    pub pieces: Vec<ExpandablePiece>,
+    pub compressions: usize,
+}
+
+impl ExpandableMessage {
+    pub fn new(hash: &WeakHash, state: &[u8], k: usize) -> ExpandableMessage {
+        let mut pieces: Vec<ExpandablePiece> = Vec::new();
+        let mut compressions = 0usize;
+        let mut state = state.to_vec();
+        for i in 0..k {
+            let (piece, count) = find_expandable_piece(hash, &state, k - 1 - i);
+            state = piece.next_state.clone();
+            pieces.push(piece);
+            compressions += count;
+        }
+        ExpandableMessage {
+            pieces,
+            compressions,
+        }
+    }
+
+    pub fn final_state(&self) -> Option<&[u8]> {
+        self.pieces.last().map(|piece| &piece.next_state[..])
+    }
+
+    pub fn min_blocks(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn max_blocks(&self) -> usize {
+        self.pieces.len() + (1usize << self.pieces.len()) - 1
+    }
+
+    /// The message of exactly `blocks` blocks, the extra blocks over the
+    /// minimum are written in binary to pick the long pieces.
+    pub fn message(&self, blocks: usize) -> Result<Vec<u8>, &'static str> {
+        if blocks < self.min_blocks() || blocks > self.max_blocks() {
+            return Err("Length is outside the range of the expandable message");
+        }
+        let extra = blocks - self.min_blocks();
+        let k = self.pieces.len();
+        Ok(self
+            .pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(i, piece)| match (extra >> (k - 1 - i)) & 1 {
+                1 => piece.long.clone(),
+                _ => piece.short.clone(),
+            })
+            .collect())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecondPreimage {
+    pub message: Vec<u8>,
+    /// Compression calls spent building the expandable message.
+    pub expandable_compressions: usize,
+    /// Compression calls spent searching for the bridge block.
+    pub bridge_compressions: usize,
+}
+
+/// Second preimage for a long message. Build an expandable message, then
+/// search for a bridge block from its final state into any of the
+/// intermediate states of the original. The expandable message is sized to
+/// fill everything before the bridge, so the forgery has the original's
+/// length and the length padding no longer protects it. For a b bit state
+/// and 2^k blocks this costs about k * 2^(b/2) + 2^(b - k) compressions
+/// instead of 2^b.
+pub fn kelsey_schneier_second_preimage(
+    hash: &WeakHash,
+    message: &[u8],
+    k: usize,
+) -> Result<SecondPreimage, &'static str> {
+    let full_blocks = message.len() / WEAK_HASH_BLOCK_SIZE;
+    if full_blocks <= k {
+        return Err("Message is too short for the expandable message");
+    }
+
+    // State after block i of the original, for the bridge targets an
+    // expandable message can reach
+    let mut targets: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut state = hash.initial_state.clone();
+    for (i, block) in message.chunks_exact(WEAK_HASH_BLOCK_SIZE).enumerate() {
+        state = hash.compress(&state, block);
+        let blocks = i + 1;
+        if blocks > k && blocks <= k + (1usize << k) {
+            targets.entry(state.clone()).or_insert(blocks);
+        }
+    }
+
+    let expandable = ExpandableMessage::new(hash, &hash.initial_state, k);
+    let expandable_state = expandable.final_state().unwrap().to_vec();
+    let mut rng = thread_rng();
+    let mut bridge_compressions = 0usize;
+    loop {
+        let bridge: [u8; WEAK_HASH_BLOCK_SIZE] = rng.gen();
+        bridge_compressions += 1;
+        if let Some(blocks) = targets.get(&hash.compress(&expandable_state, &bridge)) {
+            let mut forged = expandable.message(blocks - 1)?;
+            forged.extend_from_slice(&bridge);
+            forged.extend_from_slice(&message[blocks * WEAK_HASH_BLOCK_SIZE..]);
+            return Ok(SecondPreimage {
+                message: forged,
+                expandable_compressions: expandable.compressions,
+                bridge_compressions,
+            });
+        }
+    }
+}
+
+/// The diamond structure behind the Nostradamus attack: 2^k leaf states
+/// herded pairwise through k levels of collisions into a single root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiamondStructure {
+    /// levels[0] holds the leaves and levels[k] the root.
+    pub levels: Vec<Vec<Vec<u8>>>,
+    /// blocks[i][j] takes levels[i][j] to levels[i + 1][j / 2].
+    pub blocks: Vec<Vec<Vec<u8>>>,
+    pub compressions: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HerdedMessage {
+    pub message: Vec<u8>,
+    /// Compression calls spent searching for the block linking into the
+    /// diamond.
+    pub link_compressions: usize,
+}
+
+impl DiamondStructure {
+    /// Build a diamond over 2^k random leaf states, costing about
+    /// 2^k * 2^(b/2) compressions for a b bit state.
+    pub fn new(hash: &WeakHash, k: usize) -> DiamondStructure {
+        let mut rng = thread_rng();
+        let mut leaves: Vec<Vec<u8>> = Vec::new();
+        while leaves.len() < 1 << k {
+            let leaf: Vec<u8> = (0..hash.state_size()).map(|_| rng.gen()).collect();
+            if !leaves.contains(&leaf) {
+                leaves.push(leaf);
+            }
+        }
+
+        let mut diamond = DiamondStructure {
+            levels: vec![leaves],
+            blocks: Vec::new(),
+            compressions: 0,
+        };
+        for _ in 0..k {
+            let mut states: Vec<Vec<u8>> = Vec::new();
+            let mut blocks: Vec<Vec<u8>> = Vec::new();
+            for pair in diamond.levels.last().unwrap().chunks(2) {
+                let (x, y, state, compressions) = find_cross_collision(hash, &pair[0], &pair[1]);
+                blocks.push(x);
+                blocks.push(y);
+                states.push(state);
+                diamond.compressions += compressions;
+            }
+            diamond.levels.push(states);
+            diamond.blocks.push(blocks);
+        }
+        diamond
+    }
+
+    pub fn k(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.levels[self.k()][0]
+    }
+
+    /// The k blocks that lead from a leaf to the root.
+    pub fn path(&self, leaf: usize) -> Vec<u8> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(level, blocks)| blocks[leaf >> level].clone())
+            .collect()
+    }
+
+    /// The digest to publish before the prediction is known. The prediction
+    /// has to be exactly `prediction_blocks` long, as the length padding
+    /// after the root is fixed now.
+    pub fn commitment(&self, hash: &WeakHash, prediction_blocks: usize) -> Vec<u8> {
+        let message_len = (prediction_blocks + 1 + self.k()) * WEAK_HASH_BLOCK_SIZE;
+        hash.process(self.root(), &weak_hash_padding(message_len))
+    }
+
+    /// Append a linking block into one of the leaves and the leaf's path to
+    /// the root to the prediction, the result hashes to the commitment. The
+    /// prediction has to be a whole number of blocks. Finding the link takes
+    /// about 2^(b - k) compressions.
+    pub fn herd(&self, hash: &WeakHash, prediction: &[u8]) -> Result<HerdedMessage, &'static str> {
+        if !prediction.len().is_multiple_of(WEAK_HASH_BLOCK_SIZE) {
+            return Err("Prediction has to be a whole number of blocks");
+        }
+        let leaves: HashMap<&Vec<u8>, usize> = self.levels[0]
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| (leaf, index))
+            .collect();
+        let state = hash.process(&hash.initial_state, prediction);
+
+        let mut rng = thread_rng();
+        let mut link_compressions = 0usize;
+        loop {
+            let link: [u8; WEAK_HASH_BLOCK_SIZE] = rng.gen();
+            link_compressions += 1;
+            if let Some(leaf) = leaves.get(&hash.compress(&state, &link)) {
+                let mut message = prediction.to_vec();
+                message.extend_from_slice(&link);
+                message.extend(self.path(*leaf));
+                return Ok(HerdedMessage {
+                    message,
+                    link_compressions,
+                });
+            }
+        }
+    }
+
+    /// Serialise as the state size and k, then the leaves and the blocks of
+    /// every level. The inner states are recomputed on loading.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.levels[0][0].len() as u8, self.k() as u8];
+        bytes.extend(self.levels[0].iter().flatten());
+        bytes.extend(self.blocks.iter().flatten().flatten());
+        bytes
+    }
+
+    pub fn from_bytes(hash: &WeakHash, bytes: &[u8]) -> Result<DiamondStructure, &'static str> {
+        if bytes.len() < 2 || bytes[0] as usize != hash.state_size() {
+            return Err("Diamond structure was built for a different hash");
+        }
+        let (state_size, k) = (bytes[0] as usize, bytes[1] as usize);
+        if k >= usize::BITS as usize - 1 {
+            return Err("Diamond structure has the wrong length");
+        }
+        let leaves_len = state_size
+            .checked_mul(1usize << k)
+            .ok_or("Diamond structure has the wrong length")?;
+        let blocks_len = ((1usize << (k + 1)) - 2)
+            .checked_mul(WEAK_HASH_BLOCK_SIZE)
+            .ok_or("Diamond structure has the wrong length")?;
+        if Some(bytes.len()) != leaves_len.checked_add(blocks_len + 2) {
+            return Err("Diamond structure has the wrong length");
+        }
+
+        let leaves: Vec<Vec<u8>> = bytes[2..2 + leaves_len]
+            .chunks(state_size)
+            .map(|leaf| leaf.to_vec())
+            .collect();
+        let mut diamond = DiamondStructure {
+            levels: vec![leaves],
+            blocks: Vec::new(),
+            compressions: 0,
+        };
+        let mut blocks = bytes[2 + leaves_len..].chunks(WEAK_HASH_BLOCK_SIZE);
+        for level in 0..k {
+            let level_blocks: Vec<Vec<u8>> = (0..1usize << (k - level))
+                .map(|_| blocks.next().unwrap().to_vec())
+                .collect();
+            let mut states: Vec<Vec<u8>> = Vec::new();
+            for (pair, pair_blocks) in diamond.levels[level].chunks(2).zip(level_blocks.chunks(2)) {
+                let state = hash.compress(&pair[0], &pair_blocks[0]);
+                if hash.compress(&pair[1], &pair_blocks[1]) != state {
+                    return Err("Diamond structure blocks do not collide");
+                }
+                states.push(state);
+            }
+            diamond.levels.push(states);
+            diamond.blocks.push(level_blocks);
+        }
+        Ok(diamond)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(hash: &WeakHash, path: &str) -> Result<DiamondStructure, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        DiamondStructure::from_bytes(hash, &bytes).map_err(|e| e.to_string())
+    }
+}